@@ -1,8 +1,43 @@
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UnshredConfig {
+    /// IPv4 or IPv6 socket address, `[::]:<port>` binds dual-stack.
+    /// Only used when `inputs` is empty and neither a `ShredSource` nor ingest is added.
     pub bind_address: String,
+    /// Named shred feeds, each with its own sockets, all feeding the same FEC workers
+    #[serde(default)]
+    pub inputs: Vec<ShredInputConfig>,
+    /// Number of receiver threads, each with its own `SO_REUSEPORT` socket
+    pub num_receivers: Option<u8>,
+    /// Max datagrams pulled per `recvmmsg` call
+    pub recv_batch_size: Option<usize>,
+    /// Clock `received_at_micros` is taken from
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
+    /// Capture every received datagram to disk
+    #[serde(default)]
+    pub recording: Option<RecordingConfig>,
+    /// Forward received shreds to downstream UDP destinations
+    #[serde(default)]
+    pub relay: Option<RelayConfig>,
+    /// Drop shreds by sender address
+    #[serde(default)]
+    pub source_filter: Option<SourceFilterConfig>,
+    /// Drop shreds of other clusters, all versions are accepted if unset
+    #[serde(default)]
+    pub shred_version: Option<ShredVersionConfig>,
+    /// Queue capacities and what happens when they fill up
+    #[serde(default)]
+    pub backpressure: BackpressureConfig,
+    /// Drop shreds, FEC sets and batches of slots further than this behind the highest
+    /// slot with a completed FEC set, so catching up after a stall starts with the newest slots.
+    /// Without a leader schedule, far jumps of that slot need several slots to confirm them.
+    pub max_slot_lag: Option<u64>,
+    /// Completed FEC sets are remembered for this many slots behind the highest
+    /// completed slot, as tracked for `max_slot_lag`, to drop their remaining shreds. 512 by default.
+    pub dedup_window_slots: Option<u64>,
     pub num_fec_workers: Option<u8>,
     pub num_batch_workers: Option<u8>,
 }
@@ -11,8 +46,171 @@ impl Default for UnshredConfig {
     fn default() -> Self {
         Self {
             bind_address: "0.0.0.0:8001".to_string(),
+            inputs: Vec::new(),
+            num_receivers: None,
+            recv_batch_size: None,
+            timestamp_source: TimestampSource::default(),
+            recording: None,
+            relay: None,
+            source_filter: None,
+            shred_version: None,
+            backpressure: BackpressureConfig::default(),
+            max_slot_lag: None,
+            dedup_window_slots: None,
             num_fec_workers: None,
             num_batch_workers: None,
         }
     }
 }
+
+impl UnshredConfig {
+    /// Configured inputs, or a single `default` input on `bind_address`
+    pub fn resolved_inputs(&self) -> Vec<ShredInputConfig> {
+        if self.inputs.is_empty() {
+            vec![ShredInputConfig {
+                name: "default".to_string(),
+                bind_address: self.bind_address.clone(),
+                multicast: None,
+            }]
+        } else {
+            self.inputs.clone()
+        }
+    }
+}
+
+/// Shred versions accepted by the receivers, e.g. to keep testnet shreds leaking onto
+/// the port out of mainnet FEC sets. Dropped shreds are counted, not per version.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShredVersionConfig {
+    /// Accept only these shred versions
+    Fixed(Vec<u16>),
+    /// Lock onto the majority version of the first `sample_size` shreds (1000 by default),
+    /// which are all accepted
+    AutoDetect { sample_size: Option<usize> },
+}
+
+/// Clock used to stamp `received_at_micros`, all in microseconds since the Unix epoch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    /// `SystemTime::now()` once per receive batch, after the datagrams left the socket buffer
+    #[default]
+    System,
+    /// Kernel software arrival time (`SO_TIMESTAMPNS`), Linux only
+    Kernel,
+    /// NIC arrival time (`SO_TIMESTAMPING`), Linux only. Requires RX timestamping
+    /// enabled on the NIC and its clock synced to the system clock (e.g. `phc2sys`).
+    /// Datagrams the NIC didn't stamp fall back to kernel software time.
+    Hardware,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShredInputConfig {
+    /// Used in logs and metric labels, e.g. `tvu` or `relay-a`
+    pub name: String,
+    /// IPv4 or IPv6 socket address, `[::]:<port>` binds dual-stack
+    pub bind_address: String,
+    /// Multicast groups to join on this input
+    #[serde(default)]
+    pub multicast: Option<MulticastConfig>,
+}
+
+/// Multicast membership for an input.
+///
+/// `bind_address` should carry the group port, bound to either the group
+/// address or the unspecified address. Multicast inputs use a single socket,
+/// since every `SO_REUSEPORT` socket would receive its own copy of each datagram.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MulticastConfig {
+    /// Groups to join, same address family as `bind_address`
+    pub groups: Vec<IpAddr>,
+    /// Interface name (e.g. `eth0`, or `lo` for local testing) or, for IPv4,
+    /// an address assigned to it. The system picks one if unset.
+    pub interface: Option<String>,
+    /// Source-specific multicast: only receive the groups from this sender
+    pub source: Option<IpAddr>,
+}
+
+/// Rotating recording files of every received datagram, with its arrival time and feed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordingConfig {
+    /// Directory the files are written to, created if missing
+    pub directory: String,
+    /// File name prefix, `shreds` by default
+    pub prefix: Option<String>,
+    /// Rotate to a new file after this many uncompressed bytes, 1GB by default
+    pub max_file_bytes: Option<u64>,
+    /// Delete the oldest files beyond this many, all are kept if unset
+    pub max_files: Option<usize>,
+    /// zstd compression level, requires `features = ["zstd"]`
+    pub zstd_level: Option<i32>,
+}
+
+/// Shred relay: forwards every accepted shred to downstream UDP destinations.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RelayConfig {
+    /// IPv4 or IPv6 socket addresses to forward to
+    pub destinations: Vec<String>,
+    /// Forward each (slot, index, shred type) once, however many feeds deliver it
+    #[serde(default)]
+    pub dedup: bool,
+    /// Also forward data shreds recovered from code shreds, so downstream gets
+    /// complete FEC sets even when the upstream feeds dropped packets
+    #[serde(default)]
+    pub forward_recovered: bool,
+}
+
+/// Sender address checks applied to every shred with a known sender, before it is
+/// recorded, relayed or enqueued. Addresses are matched by IP, ignoring the port.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SourceFilterConfig {
+    /// Only accept these networks, e.g. `10.0.0.0/8` or `2001:db8::/32`, all if empty
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never accept these networks, takes precedence over `allow`
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Drop datagrams beyond this many per second from a single sender
+    pub max_packets_per_second: Option<u32>,
+    /// Quarantine a sender once this many of its shreds failed to parse within a minute
+    pub max_parse_failures: Option<u32>,
+    /// How long quarantined senders are dropped, 300 seconds by default
+    pub quarantine_secs: Option<u64>,
+}
+
+/// Queues between the pipeline stages
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BackpressureConfig {
+    /// Sources -> FEC workers, one queue per worker. 10000 shreds and `DropNewest` by default.
+    #[serde(default)]
+    pub fec_workers: StageConfig,
+    /// FEC workers -> batch dispatcher. 1000 FEC sets and `Block` by default.
+    #[serde(default)]
+    pub dispatch: StageConfig,
+    /// Batch dispatcher -> batch workers, one queue per worker. 10000 batches and `Block` by default.
+    #[serde(default)]
+    pub batch_workers: StageConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StageConfig {
+    /// Queue capacity
+    pub capacity: Option<usize>,
+    /// Applied when the queue is full
+    pub policy: Option<BackpressurePolicy>,
+}
+
+/// What a full queue does with new items. Dropped items are counted per stage and
+/// reason in `stage_dropped_total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressurePolicy {
+    /// Drop the new item
+    DropNewest,
+    /// Drop every queued item of the oldest queued slot to make room,
+    /// or the new item if it belongs to that slot
+    DropOldestSlot,
+    /// Wait for room, dropping the new item after `timeout_ms` if set
+    Block { timeout_ms: Option<u64> },
+}
@@ -0,0 +1,300 @@
+use crate::{
+    events::{IncompleteFecSet, SlotIncompleteEvent},
+    processor::SlotProgress,
+};
+
+use ahash::{HashMap, HashSet};
+use dashmap::DashMap;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+// Time the FEC workers get to drop the unfinished FEC sets of an evicted slot
+const REPORT_DELAY: Duration = Duration::from_secs(5);
+// Slots behind the tip whose report is remembered
+const REPORTED_SLOTS_WINDOW: u64 = 512;
+
+/// FEC sets the FEC workers dropped before reaching the recovery threshold, per slot
+#[derive(Debug, Default)]
+pub struct IncompleteFecSets {
+    slots: DashMap<u64, Vec<IncompleteFecSet>>,
+}
+
+impl IncompleteFecSets {
+    pub fn record(&self, slot: u64, fec_set: IncompleteFecSet) {
+        self.slots.entry(slot).or_default().push(fec_set);
+    }
+
+    fn take(&self, slot: u64) -> Vec<IncompleteFecSet> {
+        self.slots
+            .remove(&slot)
+            .map(|(_, fec_sets)| fec_sets)
+            .unwrap_or_default()
+    }
+
+    fn slots(&self) -> Vec<u64> {
+        self.slots.iter().map(|entry| *entry.key()).collect()
+    }
+
+    fn prune_below(&self, slot: u64) {
+        self.slots.retain(|s, _| *s >= slot);
+    }
+}
+
+/// What the dispatcher knew of a slot when evicting it
+pub struct EvictedSlot {
+    /// Indices of the data shreds of completed FEC sets
+    pub data_shreds: Vec<u32>,
+    pub last_shred_idx: Option<u32>,
+    pub progress: Arc<SlotProgress>,
+}
+
+struct PendingSlot {
+    // `None` if no FEC set of the slot ever completed
+    evicted: Option<EvictedSlot>,
+    since: Instant,
+}
+
+/// Builds `SlotIncompleteEvent`s for the dispatcher. Evicted slots wait `REPORT_DELAY`
+/// so the FEC workers, which evict on their own clock, have recorded the slot's
+/// unfinished FEC sets by then.
+pub struct IncompleteSlots {
+    incomplete_fec_sets: Arc<IncompleteFecSets>,
+    pending: HashMap<u64, PendingSlot>,
+    reported: HashSet<u64>,
+}
+
+impl IncompleteSlots {
+    pub fn new(incomplete_fec_sets: Arc<IncompleteFecSets>) -> Self {
+        Self {
+            incomplete_fec_sets,
+            pending: HashMap::default(),
+            reported: HashSet::default(),
+        }
+    }
+
+    pub fn evict(&mut self, slot: u64, evicted: EvictedSlot) {
+        self.pending.insert(
+            slot,
+            PendingSlot {
+                evicted: Some(evicted),
+                since: Instant::now(),
+            },
+        );
+    }
+
+    /// Waits up to `timeout` for the batches of evicted slots still decoding
+    pub async fn settle(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline
+            && self
+                .pending
+                .values()
+                .filter_map(|pending| pending.evicted.as_ref())
+                .any(|evicted| !evicted.progress.settled())
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Reports the pending slots that waited long enough, or all of them on `flush`.
+    /// Slots for which `is_active` holds are still being accumulated.
+    pub fn take_due(
+        &mut self,
+        is_active: impl Fn(u64) -> bool,
+        highest_slot: u64,
+        flush: bool,
+    ) -> Vec<SlotIncompleteEvent> {
+        let floor = highest_slot.saturating_sub(REPORTED_SLOTS_WINDOW);
+        self.reported.retain(|slot| *slot >= floor);
+        self.incomplete_fec_sets.prune_below(floor);
+
+        let now = Instant::now();
+        for slot in self.incomplete_fec_sets.slots() {
+            if is_active(slot) || self.pending.contains_key(&slot) {
+                continue;
+            }
+            if self.reported.contains(&slot) {
+                // Dropped after the slot was reported
+                self.incomplete_fec_sets.take(slot);
+                continue;
+            }
+            self.pending.insert(
+                slot,
+                PendingSlot {
+                    evicted: None,
+                    since: now,
+                },
+            );
+        }
+
+        let due: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| flush || now.duration_since(pending.since) >= REPORT_DELAY)
+            .map(|(slot, _)| *slot)
+            .collect();
+
+        let mut events = Vec::new();
+        for slot in due {
+            let Some(pending) = self.pending.remove(&slot) else {
+                continue;
+            };
+            self.reported.insert(slot);
+            let fec_sets = self.incomplete_fec_sets.take(slot);
+            if let Some(event) = Self::build_event(slot, pending.evicted, fec_sets) {
+                events.push(event);
+            }
+        }
+        events.sort_unstable_by_key(|event| event.slot);
+
+        events
+    }
+
+    fn build_event(
+        slot: u64,
+        evicted: Option<EvictedSlot>,
+        mut incomplete_fec_sets: Vec<IncompleteFecSet>,
+    ) -> Option<SlotIncompleteEvent> {
+        // Batches may still have been decoding at eviction, checked once the report is due
+        let (data_shreds, last_shred_idx, transactions, failed_batches) = match evicted {
+            Some(evicted) if evicted.progress.decoded() => return None,
+            Some(evicted) => (
+                evicted.data_shreds,
+                evicted.last_shred_idx,
+                evicted.progress.transactions(),
+                evicted.progress.failed_batches(),
+            ),
+            None => (Vec::new(), None, 0, 0),
+        };
+        incomplete_fec_sets.sort_unstable_by_key(|fec_set| fec_set.fec_set_index);
+
+        let received: HashSet<u32> = data_shreds
+            .into_iter()
+            .chain(
+                incomplete_fec_sets
+                    .iter()
+                    .flat_map(|fec_set| fec_set.data_shreds.iter().copied()),
+            )
+            .collect();
+        let highest_idx = last_shred_idx.or_else(|| {
+            received
+                .iter()
+                .copied()
+                .chain(incomplete_fec_sets.iter().map(|fec_set| {
+                    // Last index of the FEC set, if a code shred told its size
+                    fec_set.fec_set_index
+                        + fec_set.expected_data_shreds.unwrap_or(1).saturating_sub(1)
+                }))
+                .max()
+        });
+        let missing_data_shreds = highest_idx
+            .map(|highest_idx| {
+                (0..=highest_idx)
+                    .filter(|idx| !received.contains(idx))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(SlotIncompleteEvent {
+            slot,
+            last_shred_idx,
+            missing_data_shreds,
+            incomplete_fec_sets,
+            transactions,
+            failed_batches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incomplete_fec_set(
+        fec_set_index: u32,
+        data_shreds: Vec<u32>,
+        expected_data_shreds: Option<u32>,
+    ) -> IncompleteFecSet {
+        IncompleteFecSet {
+            fec_set_index,
+            data_shreds,
+            code_shreds: expected_data_shreds.map_or(0, |_| 1),
+            expected_data_shreds,
+        }
+    }
+
+    fn evicted(data_shreds: Vec<u32>, last_shred_idx: Option<u32>) -> EvictedSlot {
+        EvictedSlot {
+            data_shreds,
+            last_shred_idx,
+            progress: Arc::new(SlotProgress::new()),
+        }
+    }
+
+    #[test]
+    fn missing_shreds_up_to_last_shred() {
+        let event = IncompleteSlots::build_event(
+            7,
+            Some(evicted((0..4).chain(8..10).collect(), Some(11))),
+            vec![incomplete_fec_set(4, vec![5, 6], Some(4))],
+        )
+        .unwrap();
+
+        assert_eq!(event.slot, 7);
+        assert_eq!(event.last_shred_idx, Some(11));
+        assert_eq!(event.missing_data_shreds, vec![4, 7, 10, 11]);
+        assert_eq!(event.incomplete_fec_sets.len(), 1);
+    }
+
+    #[test]
+    fn missing_shreds_up_to_end_of_incomplete_fec_sets() {
+        let event = IncompleteSlots::build_event(
+            7,
+            None,
+            vec![
+                incomplete_fec_set(32, vec![33], Some(32)),
+                incomplete_fec_set(0, vec![0, 2], None),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(event.last_shred_idx, None);
+        let expected: Vec<u32> = [1].into_iter().chain(3..33).chain(34..64).collect();
+        assert_eq!(event.missing_data_shreds, expected);
+        let fec_set_indices: Vec<u32> = event
+            .incomplete_fec_sets
+            .iter()
+            .map(|fec_set| fec_set.fec_set_index)
+            .collect();
+        assert_eq!(fec_set_indices, vec![0, 32]);
+    }
+
+    #[test]
+    fn missing_shreds_up_to_highest_received() {
+        let event =
+            IncompleteSlots::build_event(7, None, vec![incomplete_fec_set(0, vec![1, 3], None)])
+                .unwrap();
+
+        assert_eq!(event.missing_data_shreds, vec![0, 2]);
+        assert_eq!(event.transactions, 0);
+    }
+
+    #[test]
+    fn evicted_slot_waits_for_report_delay() {
+        let incomplete_fec_sets = Arc::new(IncompleteFecSets::default());
+        let mut slots = IncompleteSlots::new(Arc::clone(&incomplete_fec_sets));
+        slots.evict(7, evicted(vec![0], Some(1)));
+        incomplete_fec_sets.record(8, incomplete_fec_set(0, vec![0], None));
+
+        assert!(slots.take_due(|_| false, 8, false).is_empty());
+        let events = slots.take_due(|_| false, 8, true);
+        let slots_reported: Vec<u64> = events.iter().map(|event| event.slot).collect();
+        assert_eq!(slots_reported, vec![7, 8]);
+
+        // Dropped after the report
+        incomplete_fec_sets.record(8, incomplete_fec_set(32, vec![32], None));
+        assert!(slots.take_due(|_| false, 8, true).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::header::SIGNATURE_LEN;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::tip::SlotTip;
+
+use ahash::HashMap;
+use dashmap::DashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+const DEFAULT_WINDOW_SLOTS: u64 = 512;
+
+/// Completed FEC sets of the most recent slots, for dropping their remaining shreds.
+///
+/// Slots more than `window_slots` behind the slot tip are evicted, a shred of an evicted
+/// slot starts a new FEC set accumulator instead. The window follows the tip rather than
+/// the inserted slots, so a forged FEC set far ahead cannot empty it.
+pub struct ProcessedFecSets {
+    slots: DashMap<u64, HashMap<u32, Vec<[u8; SIGNATURE_LEN]>>>, // slot -> fec set index -> signatures
+    window_slots: u64,
+    slot_tip: Arc<SlotTip>,
+    // Slots below are evicted
+    floor: AtomicU64,
+}
+
+impl ProcessedFecSets {
+    pub fn new(window_slots: Option<u64>, slot_tip: Arc<SlotTip>) -> Self {
+        Self {
+            slots: DashMap::new(),
+            window_slots: std::cmp::max(window_slots.unwrap_or(DEFAULT_WINDOW_SLOTS), 1),
+            slot_tip,
+            floor: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether the FEC set completed with shreds carrying `signature`, takes a read lock on one shard only.
+    /// Both versions of an equivocated FEC set may complete, each signed differently.
+    pub fn contains_signed(&self, (slot, fec_set_index): &(u64, u32), signature: &[u8]) -> bool {
+        self.slots.get(slot).is_some_and(|fec_sets| {
+            fec_sets.get(fec_set_index).is_some_and(|completed| {
+                completed
+                    .iter()
+                    .any(|completed| completed[..] == *signature)
+            })
+        })
+    }
+
+    /// Records a completed FEC set, after the tip observed its slot
+    pub fn insert(&self, (slot, fec_set_index): (u64, u32), signature: [u8; SIGNATURE_LEN]) {
+        self.evict(self.slot_tip.highest().saturating_sub(self.window_slots));
+        if slot < self.floor.load(Ordering::Relaxed) {
+            return; // Already out of the window
+        }
+        // Would stay until the tip catches up
+        if !self.slot_tip.within_reach(slot) {
+            return;
+        }
+        let mut fec_sets = self.slots.entry(slot).or_default();
+        let completed = fec_sets.entry(fec_set_index).or_default();
+        if !completed.contains(&signature) {
+            completed.push(signature);
+        }
+    }
+
+    /// Drops slots below `floor`, once per floor
+    fn evict(&self, floor: u64) {
+        let previous_floor = self.floor.fetch_max(floor, Ordering::Relaxed);
+        if floor <= previous_floor {
+            return;
+        }
+
+        self.slots.retain(|slot, _| *slot >= floor);
+        #[cfg(feature = "metrics")]
+        self.update_metrics();
+    }
+
+    #[cfg(feature = "metrics")]
+    fn update_metrics(&self) {
+        if let Some(metrics) = Metrics::try_get() {
+            let fec_sets: usize = self
+                .slots
+                .iter()
+                .map(|fec_sets| fec_sets.values().map(Vec::len).sum::<usize>())
+                .sum();
+            metrics
+                .processed_fec_sets
+                .with_label_values(&["fec_sets"])
+                .set(fec_sets as i64);
+            metrics
+                .processed_fec_sets
+                .with_label_values(&["slots"])
+                .set(self.slots.len() as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(byte: u8) -> [u8; SIGNATURE_LEN] {
+        [byte; SIGNATURE_LEN]
+    }
+
+    /// Completes a FEC set the way the FEC workers do
+    fn complete(fec_sets: &ProcessedFecSets, slot_tip: &SlotTip, slot: u64) {
+        slot_tip.observe(slot);
+        fec_sets.insert((slot, 0), signature(1));
+    }
+
+    #[test]
+    fn matches_signature() {
+        let slot_tip = Arc::new(SlotTip::new(None, true));
+        let fec_sets = ProcessedFecSets::new(None, Arc::clone(&slot_tip));
+        complete(&fec_sets, &slot_tip, 100);
+
+        assert!(fec_sets.contains_signed(&(100, 0), &signature(1)));
+        assert!(!fec_sets.contains_signed(&(100, 0), &signature(2)));
+        assert!(!fec_sets.contains_signed(&(100, 32), &signature(1)));
+    }
+
+    #[test]
+    fn evicts_slots_behind_window() {
+        let slot_tip = Arc::new(SlotTip::new(None, true));
+        let fec_sets = ProcessedFecSets::new(Some(10), Arc::clone(&slot_tip));
+        for slot in [100, 105, 110] {
+            complete(&fec_sets, &slot_tip, slot);
+        }
+        assert!(fec_sets.contains_signed(&(100, 0), &signature(1)));
+
+        complete(&fec_sets, &slot_tip, 111);
+        assert!(!fec_sets.contains_signed(&(100, 0), &signature(1)));
+        assert!(fec_sets.contains_signed(&(105, 0), &signature(1)));
+
+        // Out of the window already
+        fec_sets.insert((100, 32), signature(1));
+        assert!(!fec_sets.contains_signed(&(100, 32), &signature(1)));
+    }
+
+    #[test]
+    fn far_slot_keeps_window() {
+        let slot_tip = Arc::new(SlotTip::new(None, false));
+        let fec_sets = ProcessedFecSets::new(Some(10), Arc::clone(&slot_tip));
+        for slot in 100..104 {
+            complete(&fec_sets, &slot_tip, slot);
+        }
+
+        complete(&fec_sets, &slot_tip, 1_000_000);
+        assert!(!fec_sets.contains_signed(&(1_000_000, 0), &signature(1)));
+        assert!(fec_sets.contains_signed(&(100, 0), &signature(1)));
+
+        complete(&fec_sets, &slot_tip, 104);
+        assert!(fec_sets.contains_signed(&(104, 0), &signature(1)));
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{events::DuplicateSlotEvent, tip::SlotTip};
+
+use ahash::HashMap;
+use dashmap::DashSet;
+use solana_hash::Hash;
+use solana_ledger::shred::Shred;
+use std::{
+    collections::hash_map::Entry,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+// Slots behind the tip whose duplicate flag is kept
+const DUPLICATE_SLOTS_WINDOW: u64 = 512;
+
+/// Slots with conflicting shreds, shared by the FEC workers and the dispatcher
+#[derive(Debug, Default)]
+pub struct DuplicateSlots {
+    // Reported to the handler, once per slot
+    reported: DashSet<u64>,
+    // Both conflicting shreds signed by the slot leader
+    equivocated: DashSet<u64>,
+}
+
+impl DuplicateSlots {
+    /// Whether the leader of `slot` is proven to have equivocated
+    pub fn contains(&self, slot: u64) -> bool {
+        self.equivocated.contains(&slot)
+    }
+
+    /// Marks `slot` reported, `false` if it was reported already
+    fn report(&self, slot: u64) -> bool {
+        self.reported.insert(slot)
+    }
+
+    /// Forgets slots far behind `highest_slot`
+    pub fn prune(&self, highest_slot: u64) {
+        let floor = highest_slot.saturating_sub(DUPLICATE_SLOTS_WINDOW);
+        self.reported.retain(|slot| *slot >= floor);
+        self.equivocated.retain(|slot| *slot >= floor);
+    }
+}
+
+/// First shred of a FEC set with its merkle root
+struct FirstShred {
+    merkle_root: Hash,
+    shred: Shred,
+    seen_at: Instant,
+}
+
+/// Detects equivocation per FEC worker: shreds of one FEC set with different merkle roots.
+/// Keeps the first shred of every recent FEC set as one half of the evidence.
+pub struct DuplicateDetector {
+    first_shreds: HashMap<(u64, u32), FirstShred>,
+    duplicate_slots: Arc<DuplicateSlots>,
+    // Every checked shred is signed by its slot leader
+    verified: bool,
+}
+
+impl DuplicateDetector {
+    /// * `verified` - only shreds verified against the leader schedule are checked,
+    ///   so a conflict proves equivocation and stops the slot
+    pub fn new(duplicate_slots: Arc<DuplicateSlots>, verified: bool) -> Self {
+        Self {
+            first_shreds: HashMap::default(),
+            duplicate_slots,
+            verified,
+        }
+    }
+
+    /// Records the first merkle root of a FEC set
+    /// # Returns
+    /// * `Some(_)` - `shred` conflicts with the first root, once per slot
+    /// * `None`    - first or matching root, or the slot was reported already
+    pub fn check(
+        &mut self,
+        fec_key: (u64, u32),
+        merkle_root: Hash,
+        shred: &Shred,
+    ) -> Option<DuplicateSlotEvent> {
+        let first = match self.first_shreds.entry(fec_key) {
+            Entry::Vacant(entry) => {
+                entry.insert(FirstShred {
+                    merkle_root,
+                    shred: shred.clone(),
+                    seen_at: Instant::now(),
+                });
+                return None;
+            }
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        if first.merkle_root == merkle_root || !self.duplicate_slots.report(fec_key.0) {
+            return None;
+        }
+        if self.verified {
+            self.duplicate_slots.equivocated.insert(fec_key.0);
+        }
+
+        warn!(
+            "Duplicate slot {}: FEC set {} has merkle roots {} and {} (verified: {})",
+            fec_key.0, fec_key.1, first.merkle_root, merkle_root, self.verified
+        );
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .errors
+                .with_label_values(&["fec_worker", "duplicate_slot"])
+                .inc();
+        }
+
+        Some(DuplicateSlotEvent {
+            slot: fec_key.0,
+            fec_set_index: fec_key.1,
+            first_shred: first.shred.payload().to_vec(),
+            conflicting_shred: shred.payload().to_vec(),
+            verified: self.verified,
+        })
+    }
+
+    /// Forgets FEC sets older than `max_age` or lagging behind the tip
+    pub fn cleanup(&mut self, max_age: Duration, slot_tip: &SlotTip) {
+        let now = Instant::now();
+        self.first_shreds.retain(|(slot, _), first| {
+            now.duration_since(first.seen_at) <= max_age && !slot_tip.lags(*slot)
+        });
+        self.duplicate_slots.prune(slot_tip.highest());
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+/// Which feed delivered each slot first, emitted once when the slot is evicted
+#[derive(Debug, Clone)]
+pub struct SlotRaceEvent {
+    pub slot: u64,
+    /// Completed FEC sets the race stats were collected over
+    pub fec_sets: u32,
+    /// Feed that delivered the earliest shred of the slot
+    pub first_feed: Option<Arc<str>>,
+    /// Sorted by `fec_sets_first`, fastest feed first
+    pub feeds: Vec<FeedRaceStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedRaceStats {
+    pub feed: Arc<str>,
+    /// FEC sets for which this feed delivered the first shred
+    pub fec_sets_first: u32,
+    /// Shreds that were new to their FEC set
+    pub shreds: u32,
+    /// Shreds for an index their FEC set already had
+    pub redundant: u32,
+    /// Shreds for FEC sets that were already complete
+    pub late: u32,
+    /// Mean/max delay of this feed's first shred behind the fastest feed, per FEC set
+    pub mean_gap_micros: Option<u64>,
+    pub max_gap_micros: Option<u64>,
+}
+
+/// Emitted once every data shred of a slot is received or recovered and every batch is decoded
+#[derive(Debug, Clone)]
+pub struct SlotCompleteEvent {
+    pub slot: u64,
+    /// Data shreds of the slot, up to the last shred in slot
+    pub data_shreds: u32,
+    /// Data shreds recovered from code shreds, `0` if no recovery was needed
+    pub recovered_shreds: u32,
+    pub entries: u32,
+    pub transactions: u32,
+    /// Earliest/latest `received_at_micros` of the received data shreds
+    pub first_shred_received_at_micros: Option<u64>,
+    pub last_shred_received_at_micros: Option<u64>,
+    /// Whether every FEC set of the slot chains to the merkle root of the previous one
+    pub merkle_chain: MerkleChainStatus,
+}
+
+/// Merkle root chain of a slot's FEC sets, each carrying the root of the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleChainStatus {
+    /// Every FEC set chains to the previous one
+    Verified,
+    /// A FEC set chains to another root, the slot mixes blocks
+    Broken,
+    /// A FEC set carries no chained merkle root, the chain could not be checked
+    Unknown,
+}
+
+/// Two shreds of one FEC set with different merkle roots. Emitted once per slot.
+#[derive(Debug, Clone)]
+pub struct DuplicateSlotEvent {
+    pub slot: u64,
+    pub fec_set_index: u32,
+    /// Raw bytes of the first shred received for the FEC set
+    pub first_shred: Vec<u8>,
+    /// Raw bytes of the shred conflicting with it
+    pub conflicting_shred: Vec<u8>,
+    /// * `true`  - both shreds are signed by the slot leader, proof that it equivocated
+    /// * `false` - no leader schedule is set, either shred may be spoofed
+    pub verified: bool,
+}
+
+/// Emitted once per slot evicted before every data shred was received or recovered
+/// and every batch decoded
+#[derive(Debug, Clone)]
+pub struct SlotIncompleteEvent {
+    pub slot: u64,
+    /// Last shred in slot, `None` if its FEC set never completed
+    pub last_shred_idx: Option<u32>,
+    /// Data shred indices neither received nor recovered, up to the last shred in slot
+    /// or the highest index seen if it is unknown
+    pub missing_data_shreds: Vec<u32>,
+    /// FEC sets dropped before reaching the recovery threshold, by FEC set index
+    pub incomplete_fec_sets: Vec<IncompleteFecSet>,
+    /// Transactions of the slot delivered to `handle_transaction`
+    pub transactions: u32,
+    /// Batches shed, dropped by backpressure or failing to decode
+    pub failed_batches: u32,
+}
+
+/// FEC set dropped before enough of its shreds arrived to recover it
+#[derive(Debug, Clone)]
+pub struct IncompleteFecSet {
+    pub fec_set_index: u32,
+    /// Indices of the received data shreds, sorted
+    pub data_shreds: Vec<u32>,
+    pub code_shreds: u32,
+    /// Data shreds of the FEC set, `None` if no code shred arrived
+    pub expected_data_shreds: Option<u32>,
+}
+
+/// Events delivered to `TransactionHandler` outside of the transaction path
+#[derive(Debug)]
+pub enum SlotEvent {
+    Race(SlotRaceEvent),
+    Duplicate(DuplicateSlotEvent),
+    Complete(SlotCompleteEvent),
+    Incomplete(SlotIncompleteEvent),
+}
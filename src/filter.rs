@@ -0,0 +1,323 @@
+use crate::config::SourceFilterConfig;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_QUARANTINE: Duration = Duration::from_secs(300);
+const PRUNE_INTERVAL_SECS: u64 = 60;
+
+/// IP network in CIDR notation, a bare address is a single host
+#[derive(Debug, Clone, Copy)]
+struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    fn parse(cidr: &str) -> Result<Self> {
+        let (address, prefix_len) = match cidr.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (cidr, None),
+        };
+        let network: IpAddr = address
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid CIDR {}: {}", cidr, e))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid CIDR {}: {}", cidr, e))?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(anyhow::anyhow!("Invalid CIDR {}: prefix too long", cidr));
+        }
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Per source address state
+struct SourceState {
+    window_start: Instant,
+    packets: u32,
+    failures_since: Instant,
+    failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+/// Drops shreds by sender address: allow/deny lists, per-source rate limit and
+/// quarantine of sources sending shreds that fail to parse
+pub struct SourceFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+    max_packets_per_second: Option<u32>,
+    max_parse_failures: Option<u32>,
+    quarantine: Duration,
+    sources: DashMap<IpAddr, SourceState>,
+    started_at: Instant,
+    last_prune_secs: AtomicU64,
+}
+
+impl SourceFilter {
+    pub fn new(config: &SourceFilterConfig) -> Result<Self> {
+        Ok(Self {
+            allow: config
+                .allow
+                .iter()
+                .map(|cidr| Cidr::parse(cidr))
+                .collect::<Result<_>>()?,
+            deny: config
+                .deny
+                .iter()
+                .map(|cidr| Cidr::parse(cidr))
+                .collect::<Result<_>>()?,
+            max_packets_per_second: config.max_packets_per_second,
+            max_parse_failures: config.max_parse_failures,
+            quarantine: config
+                .quarantine_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_QUARANTINE),
+            sources: DashMap::new(),
+            started_at: Instant::now(),
+            last_prune_secs: AtomicU64::new(0),
+        })
+    }
+
+    /// Whether to accept a datagram from `ip`, counting it towards its rate limit
+    /// # Returns
+    /// * `Err(reason)` - drop reason, used as metric label
+    pub fn check(&self, ip: IpAddr) -> std::result::Result<(), &'static str> {
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return Err("source_denied");
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|cidr| cidr.contains(ip)) {
+            return Err("source_not_allowed");
+        }
+        if self.max_packets_per_second.is_none() && self.max_parse_failures.is_none() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.prune(now);
+        let mut state = self.sources.entry(ip).or_insert_with(|| SourceState {
+            window_start: now,
+            packets: 0,
+            failures_since: now,
+            failures: 0,
+            quarantined_until: None,
+        });
+
+        if let Some(quarantined_until) = state.quarantined_until {
+            if now < quarantined_until {
+                return Err("source_quarantined");
+            }
+            state.quarantined_until = None;
+        }
+
+        if let Some(max_packets_per_second) = self.max_packets_per_second {
+            if now.duration_since(state.window_start) >= RATE_WINDOW {
+                state.window_start = now;
+                state.packets = 0;
+            }
+            state.packets += 1;
+            if state.packets > max_packets_per_second {
+                return Err("source_rate_limited");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts a shred from `ip` that failed to parse, quarantining the source
+    /// once it reaches `max_parse_failures` within a minute
+    pub fn report_parse_failure(&self, ip: IpAddr) {
+        let Some(max_parse_failures) = self.max_parse_failures else {
+            return;
+        };
+        let ip = ip.to_canonical();
+        let now = Instant::now();
+        let Some(mut state) = self.sources.get_mut(&ip) else {
+            return;
+        };
+
+        if now.duration_since(state.failures_since) >= FAILURE_WINDOW {
+            state.failures_since = now;
+            state.failures = 0;
+        }
+        state.failures += 1;
+        if state.failures >= max_parse_failures && state.quarantined_until.is_none() {
+            warn!(
+                "Quarantining source {} for {:?} after {} shreds failed to parse",
+                ip, self.quarantine, state.failures
+            );
+            state.quarantined_until = Some(now + self.quarantine);
+            state.failures = 0;
+
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = Metrics::try_get() {
+                metrics
+                    .errors
+                    .with_label_values(&["receiver", "source_quarantine_started"])
+                    .inc();
+            }
+        }
+    }
+
+    /// Forgets idle sources, at most once per `PRUNE_INTERVAL_SECS`
+    fn prune(&self, now: Instant) {
+        let now_secs = now.duration_since(self.started_at).as_secs();
+        let last_prune_secs = self.last_prune_secs.load(Ordering::Relaxed);
+        if now_secs < last_prune_secs + PRUNE_INTERVAL_SECS
+            || self
+                .last_prune_secs
+                .compare_exchange(
+                    last_prune_secs,
+                    now_secs,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+        {
+            return;
+        }
+
+        self.sources.retain(|_, state| {
+            state.quarantined_until.is_some_and(|until| until > now)
+                || now.duration_since(state.window_start) < FAILURE_WINDOW
+                || now.duration_since(state.failures_since) < FAILURE_WINDOW
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    fn contains(cidr: &str, addr: &str) -> bool {
+        Cidr::parse(cidr).unwrap().contains(ip(addr))
+    }
+
+    fn source_filter(allow: &[&str], deny: &[&str]) -> SourceFilter {
+        SourceFilter::new(&SourceFilterConfig {
+            allow: allow.iter().map(|cidr| cidr.to_string()).collect(),
+            deny: deny.iter().map(|cidr| cidr.to_string()).collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn zero_prefix_contains_every_address() {
+        assert!(contains("0.0.0.0/0", "255.255.255.255"));
+        assert!(contains("10.0.0.0/0", "192.168.0.1"));
+        assert!(contains("::/0", "2001:db8::1"));
+        assert!(!contains("0.0.0.0/0", "::1"));
+    }
+
+    #[test]
+    fn full_prefix_contains_one_address() {
+        assert!(contains("10.0.0.1/32", "10.0.0.1"));
+        assert!(!contains("10.0.0.1/32", "10.0.0.2"));
+        assert!(contains("2001:db8::1/128", "2001:db8::1"));
+        assert!(!contains("2001:db8::1/128", "2001:db8::2"));
+
+        // A bare address is a single host
+        assert!(contains("10.0.0.1", "10.0.0.1"));
+        assert!(!contains("10.0.0.1", "10.0.0.2"));
+        assert!(!contains("2001:db8::1", "2001:db8::2"));
+    }
+
+    #[test]
+    fn host_bits_of_network_are_ignored() {
+        assert!(contains("10.0.0.1/8", "10.255.0.1"));
+        assert!(!contains("10.0.0.1/8", "11.0.0.1"));
+        assert!(contains("2001:db8::1/32", "2001:db8:ffff::1"));
+        assert!(!contains("2001:db8::1/32", "2001:db9::1"));
+    }
+
+    #[test]
+    fn rejects_invalid_cidrs() {
+        for cidr in [
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0.0/-1",
+            "10.0.0.0/",
+            "10.0.0.0/8/8",
+            "10.0.0/8",
+            "example.com/8",
+            "",
+        ] {
+            assert!(Cidr::parse(cidr).is_err(), "{cidr}");
+        }
+        assert!(SourceFilter::new(&SourceFilterConfig {
+            deny: vec!["10.0.0.0/40".to_string()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn allow_list() {
+        let filter = source_filter(&["10.0.0.0/8", "2001:db8::/32"], &[]);
+        assert_eq!(filter.check(ip("10.1.2.3")), Ok(()));
+        assert_eq!(filter.check(ip("2001:db8::1")), Ok(()));
+        assert_eq!(filter.check(ip("192.168.0.1")), Err("source_not_allowed"));
+
+        assert_eq!(source_filter(&[], &[]).check(ip("192.168.0.1")), Ok(()));
+    }
+
+    #[test]
+    fn deny_takes_precedence_over_allow() {
+        let filter = source_filter(&["10.0.0.0/8"], &["10.1.0.0/16"]);
+        assert_eq!(filter.check(ip("10.2.0.1")), Ok(()));
+        assert_eq!(filter.check(ip("10.1.0.1")), Err("source_denied"));
+    }
+
+    #[test]
+    fn mapped_ipv6_peers_match_ipv4_rules() {
+        let filter = source_filter(&["10.0.0.0/8"], &["10.1.0.0/16"]);
+        assert_eq!(filter.check(ip("::ffff:10.2.0.1")), Ok(()));
+        assert_eq!(filter.check(ip("::ffff:10.1.0.1")), Err("source_denied"));
+        assert_eq!(
+            filter.check(ip("::ffff:192.168.0.1")),
+            Err("source_not_allowed")
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+const QUEUE_CAPACITY: usize = 100_000;
+
+/// Bounded queue to a dedicated thread, for side outputs such as the recorder and the relay.
+/// Items are dropped rather than delaying the receivers if the thread falls behind.
+pub struct Forwarder<T> {
+    sender: Sender<T>,
+}
+
+impl<T: Send + 'static> Forwarder<T> {
+    /// Spawns thread `name` running `run` over the queued items. The queue closes,
+    /// ending `run`, once every `Forwarder` clone is dropped.
+    pub fn spawn(name: &str, run: impl FnOnce(Receiver<T>) + Send + 'static) -> Result<Self> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(QUEUE_CAPACITY);
+        std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || run(receiver))?;
+
+        Ok(Self { sender })
+    }
+
+    /// Queues `item` without waiting, `false` if it was dropped
+    pub fn try_forward(&self, item: T) -> bool {
+        self.sender.try_send(item).is_ok()
+    }
+}
+
+impl<T> Clone for Forwarder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Shred header layout, for peeking at raw shreds without parsing them.
+//!
+//! Common header: signature, variant, slot, index, shred version, FEC set index.
+//! Data shreds follow with parent offset, flags and size, code shreds with
+//! their FEC set's data and code shred counts.
+
+pub const SIGNATURE_LEN: usize = 64;
+pub const OFFSET_VARIANT: usize = 64;
+pub const OFFSET_SLOT: usize = 65;
+pub const OFFSET_INDEX: usize = 73;
+pub const OFFSET_VERSION: usize = 77;
+pub const OFFSET_FEC_SET_INDEX: usize = 79;
+// Code shreds
+pub const OFFSET_NUM_DATA: usize = 83;
+// Data shreds
+pub const OFFSET_FLAGS: usize = 85;
+pub const OFFSET_SIZE: usize = 86; // Payload total size offset
+pub const DATA_OFFSET_PAYLOAD: usize = 88;
+
+/// Shorter datagrams are not shreds
+pub const MIN_SHRED_LEN: usize = DATA_OFFSET_PAYLOAD;
+
+pub fn slot(buffer: &[u8]) -> Option<u64> {
+    read(buffer, OFFSET_SLOT).map(u64::from_le_bytes)
+}
+
+pub fn index(buffer: &[u8]) -> Option<u32> {
+    read(buffer, OFFSET_INDEX).map(u32::from_le_bytes)
+}
+
+pub fn version(buffer: &[u8]) -> Option<u16> {
+    read(buffer, OFFSET_VERSION).map(u16::from_le_bytes)
+}
+
+pub fn fec_set_index(buffer: &[u8]) -> Option<u32> {
+    read(buffer, OFFSET_FEC_SET_INDEX).map(u32::from_le_bytes)
+}
+
+/// Data shred variants have the high bit set, code shred variants don't
+pub fn is_data(buffer: &[u8]) -> Option<bool> {
+    buffer
+        .get(OFFSET_VARIANT)
+        .map(|variant| variant & 0x80 != 0)
+}
+
+fn read<const N: usize>(buffer: &[u8], offset: usize) -> Option<[u8; N]> {
+    buffer.get(offset..offset + N)?.try_into().ok()
+}
@@ -0,0 +1,86 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{source::ShredRouter, types::ShredOrigin};
+
+use anyhow::Result;
+use std::{net::SocketAddr, sync::Arc};
+use tracing::error;
+
+/// Pushes shreds received by the caller's own networking into the pipeline.
+///
+/// Shreds take the same path as those of the UDP receivers: they are sharded
+/// across the FEC workers by FEC set index and dropped if their FEC set is
+/// already complete. `run` keeps going until every clone of the handle is dropped.
+#[derive(Clone)]
+pub struct UnshredIngest {
+    router: ShredRouter,
+    feed: Arc<str>,
+}
+
+impl UnshredIngest {
+    pub(crate) fn new(router: ShredRouter) -> Self {
+        Self {
+            router,
+            feed: Arc::from("ingest"),
+        }
+    }
+
+    /// Handle that reports its shreds under feed `name` (default `ingest`)
+    pub fn with_feed(&self, name: &str) -> Self {
+        Self {
+            router: self.router.clone(),
+            feed: Arc::from(name),
+        }
+    }
+
+    /// Routes a single shred to its FEC worker
+    /// * `received_at_micros` - arrival time in micros since the Unix epoch, if known
+    /// # Returns
+    /// * `Err(_)` - malformed shred, or the pipeline is shut down. Shreds dropped by a full
+    ///   FEC worker queue are counted in `stage_dropped_total` instead.
+    pub fn push(&self, bytes: &[u8], received_at_micros: Option<u64>) -> Result<()> {
+        self.push_from(bytes, received_at_micros, None)
+    }
+
+    /// Like `push`, with the sender of the shred
+    pub fn push_from(
+        &self,
+        bytes: &[u8],
+        received_at_micros: Option<u64>,
+        peer_addr: Option<SocketAddr>,
+    ) -> Result<()> {
+        let origin = ShredOrigin {
+            feed: Arc::clone(&self.feed),
+            peer_addr,
+        };
+        self.router.route(bytes, received_at_micros, origin, false)
+    }
+
+    /// Routes every shred of a batch sharing one arrival time
+    /// # Returns
+    /// Number of shreds accepted. Failures are logged and counted like receiver errors.
+    pub fn push_batch<'a>(
+        &self,
+        shreds: impl IntoIterator<Item = &'a [u8]>,
+        received_at_micros: Option<u64>,
+    ) -> usize {
+        let mut accepted = 0;
+        for bytes in shreds {
+            match self.push(bytes, received_at_micros) {
+                Ok(()) => accepted += 1,
+                Err(e) => {
+                    error!("Ingest {} failed to process shred: {}", self.feed, e);
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = Metrics::try_get() {
+                        metrics
+                            .errors
+                            .with_label_values(&["receiver", "process_shred"])
+                            .inc();
+                    }
+                }
+            }
+        }
+
+        accepted
+    }
+}
@@ -0,0 +1,207 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use ahash::HashMap;
+use anyhow::Result;
+use solana_ledger::shred::Shred;
+use solana_pubkey::Pubkey;
+use std::{path::Path, str::FromStr, sync::Arc};
+use tracing::info;
+
+/// Slot leaders, for verifying shred signatures
+pub trait LeaderSchedule: Send + Sync + 'static {
+    /// Leader of `slot`, `None` if unknown
+    fn slot_leader(&self, slot: u64) -> Option<Pubkey>;
+}
+
+/// Leader schedule loaded from a JSON file mapping slots to base58 pubkeys,
+/// e.g. `{"312000000": "Leader111..."}`
+pub struct JsonLeaderSchedule {
+    leaders: HashMap<u64, Pubkey>,
+}
+
+impl JsonLeaderSchedule {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+        let raw: HashMap<String, String> =
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+
+        let leaders = raw
+            .into_iter()
+            .map(|(slot, leader)| {
+                let slot = slot
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid slot {}: {}", slot, e))?;
+                let leader = Pubkey::from_str(&leader)
+                    .map_err(|e| anyhow::anyhow!("Invalid leader {}: {}", leader, e))?;
+                Ok((slot, leader))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        info!(
+            "Loaded leader schedule of {} slots from {}",
+            leaders.len(),
+            path.display()
+        );
+
+        Ok(Self { leaders })
+    }
+}
+
+impl LeaderSchedule for JsonLeaderSchedule {
+    fn slot_leader(&self, slot: u64) -> Option<Pubkey> {
+        self.leaders.get(&slot).copied()
+    }
+}
+
+/// Verifies shred signatures against the slot leader.
+/// Merkle shreds sign their FEC set's merkle root, the FEC workers verify each root once.
+pub struct SignatureVerifier {
+    leader_schedule: Arc<dyn LeaderSchedule>,
+}
+
+impl SignatureVerifier {
+    pub fn new(leader_schedule: Arc<dyn LeaderSchedule>) -> Self {
+        Self { leader_schedule }
+    }
+
+    /// Whether `shred` is signed by its slot leader. Shreds of slots without
+    /// a known leader fail verification.
+    pub fn verify(&self, shred: &Shred) -> bool {
+        let Some(leader) = self.leader_schedule.slot_leader(shred.slot()) else {
+            Self::count("unknown_leader");
+            return false;
+        };
+        if !shred.verify(&leader) {
+            Self::count("invalid");
+            return false;
+        }
+        Self::count("verified");
+
+        true
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn count(outcome: &str) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics.shred_signatures.with_label_values(&[outcome]).inc();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_entry::entry::Entry;
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_ledger::shred::{ProcessShredsStats, ReedSolomonCache, Shredder};
+    use solana_signer::Signer;
+    use std::path::PathBuf;
+
+    const SLOT: u64 = 1000;
+
+    struct Leaders(HashMap<u64, Pubkey>);
+
+    impl LeaderSchedule for Leaders {
+        fn slot_leader(&self, slot: u64) -> Option<Pubkey> {
+            self.0.get(&slot).copied()
+        }
+    }
+
+    fn schedule_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "unshred-leader-schedule-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn shred(keypair: &Keypair, slot: u64) -> Shred {
+        let entries = vec![Entry {
+            num_hashes: 1,
+            hash: Hash::default(),
+            transactions: Vec::new(),
+        }];
+        Shredder::new(slot, slot - 1, 0, 0)
+            .unwrap()
+            .make_merkle_shreds_from_entries(
+                keypair,
+                &entries,
+                true,
+                Hash::default(),
+                0,
+                0,
+                &ReedSolomonCache::default(),
+                &mut ProcessShredsStats::default(),
+            )
+            .next()
+            .unwrap()
+    }
+
+    fn verifier(slot: u64, leader: Pubkey) -> SignatureVerifier {
+        let leaders = HashMap::from_iter([(slot, leader)]);
+        SignatureVerifier::new(Arc::new(Leaders(leaders)))
+    }
+
+    #[test]
+    fn load_json_schedule() {
+        let leader = Keypair::new().pubkey();
+        let path = schedule_file(
+            "valid",
+            &format!(
+                r#"{{"{}": "{}", "{}": "{}"}}"#,
+                SLOT,
+                leader,
+                SLOT + 1,
+                leader
+            ),
+        );
+        let schedule = JsonLeaderSchedule::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(schedule.slot_leader(SLOT), Some(leader));
+        assert_eq!(schedule.slot_leader(SLOT + 1), Some(leader));
+        assert_eq!(schedule.slot_leader(SLOT + 2), None);
+    }
+
+    #[test]
+    fn invalid_json_schedules_fail_to_load() {
+        let leader = Keypair::new().pubkey();
+        for (name, contents) in [
+            ("slot", format!(r#"{{"slot": "{}"}}"#, leader)),
+            ("leader", format!(r#"{{"{}": "not-a-pubkey"}}"#, SLOT)),
+            ("json", "[]".to_string()),
+        ] {
+            let path = schedule_file(name, &contents);
+            assert!(JsonLeaderSchedule::load(&path).is_err(), "{}", name);
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let missing = std::env::temp_dir().join("unshred-leader-schedule-missing.json");
+        assert!(JsonLeaderSchedule::load(missing).is_err());
+    }
+
+    #[test]
+    fn leader_signature_verifies() {
+        let leader = Keypair::new();
+        assert!(verifier(SLOT, leader.pubkey()).verify(&shred(&leader, SLOT)));
+    }
+
+    #[test]
+    fn other_signature_fails() {
+        let leader = Keypair::new();
+        assert!(!verifier(SLOT, leader.pubkey()).verify(&shred(&Keypair::new(), SLOT)));
+    }
+
+    #[test]
+    fn unknown_leader_fails() {
+        let leader = Keypair::new();
+        assert!(!verifier(SLOT, leader.pubkey()).verify(&shred(&leader, SLOT + 1)));
+    }
+}
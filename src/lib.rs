@@ -1,20 +1,49 @@
 mod config;
+mod coverage;
+mod dedup;
+mod duplicate;
+mod events;
+mod filter;
+mod forwarder;
+mod header;
+mod ingest;
+mod leader;
+mod pcap;
 mod processor;
+mod race;
 mod receiver;
+mod recording;
+mod relay;
+mod replay;
+mod source;
+mod stage;
+mod tip;
 mod types;
+mod version;
 
 #[cfg(feature = "metrics")]
 mod metrics;
 
-#[cfg(feature = "metrics")]
-use std::sync::Arc;
-
-pub use config::UnshredConfig;
+pub use config::{
+    BackpressureConfig, BackpressurePolicy, MulticastConfig, RecordingConfig, RelayConfig,
+    ShredInputConfig, ShredVersionConfig, SourceFilterConfig, StageConfig, TimestampSource,
+    UnshredConfig,
+};
+pub use events::{
+    DuplicateSlotEvent, FeedRaceStats, IncompleteFecSet, MerkleChainStatus, SlotCompleteEvent,
+    SlotIncompleteEvent, SlotRaceEvent,
+};
+pub use ingest::UnshredIngest;
+pub use leader::{JsonLeaderSchedule, LeaderSchedule};
+pub use pcap::PcapSource;
+pub use replay::{ReplayMode, ReplaySource};
+pub use source::{ShredSource, SourceShred};
 
 use anyhow::Result;
 use solana_transaction::versioned::VersionedTransaction;
+use std::sync::Arc;
 
-use crate::processor::ShredProcessor;
+use crate::processor::{FecChannels, ShredProcessor};
 
 #[derive(Debug)]
 pub struct TransactionEvent<'a> {
@@ -35,11 +64,34 @@ pub trait TransactionHandler: Send + Sync + 'static {
     /// * `Ok(())` - to continue processing
     /// * `Err(_)` - to log error and continue (does not stop processing)
     fn handle_transaction(&self, event: &TransactionEvent) -> Result<()>;
+
+    /// Called once per slot with per-feed race stats, when the slot is evicted
+    fn handle_slot_race(&self, _event: &SlotRaceEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once per slot with conflicting shreds, see `DuplicateSlotEvent::verified`
+    fn handle_duplicate_slot(&self, _event: &DuplicateSlotEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once per slot, after the transactions of its last batch were handled
+    fn handle_slot_complete(&self, _event: &SlotCompleteEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once per slot evicted before it completed, with what was missing
+    fn handle_slot_incomplete(&self, _event: &SlotIncompleteEvent) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub struct UnshredProcessor<H: TransactionHandler> {
     handler: H,
     config: UnshredConfig,
+    sources: Vec<Box<dyn ShredSource>>,
+    fec_channels: FecChannels,
+    has_ingest: bool,
 }
 
 impl<H: TransactionHandler> UnshredProcessor<H> {
@@ -48,14 +100,32 @@ impl<H: TransactionHandler> UnshredProcessor<H> {
     }
 
     pub async fn run(self) -> Result<()> {
+        // Custom sources and ingest replace the default input,
+        // explicitly configured inputs run alongside them
+        let inputs = if self.sources.is_empty() && !self.has_ingest {
+            self.config.resolved_inputs()
+        } else {
+            self.config.inputs.clone()
+        };
+
         let processor = ShredProcessor::new();
-        processor.run(self.handler, &self.config).await
+        processor
+            .run(
+                self.handler,
+                &self.config,
+                inputs,
+                self.sources,
+                self.fec_channels,
+            )
+            .await
     }
 }
 
 pub struct UnshredProcessorBuilder<H> {
     handler: Option<H>,
     config: Option<UnshredConfig>,
+    sources: Vec<Box<dyn ShredSource>>,
+    leader_schedule: Option<Arc<dyn LeaderSchedule>>,
     #[cfg(feature = "metrics")]
     metrics_registry: Option<Arc<prometheus::Registry>>,
 }
@@ -65,6 +135,8 @@ impl<H: TransactionHandler> UnshredProcessorBuilder<H> {
         Self {
             handler: None,
             config: None,
+            sources: Vec::new(),
+            leader_schedule: None,
             #[cfg(feature = "metrics")]
             metrics_registry: None,
         }
@@ -87,6 +159,127 @@ impl<H: TransactionHandler> UnshredProcessorBuilder<H> {
         self
     }
 
+    /// Adds a custom shred source. Once any source is added, `bind_address` is ignored
+    /// and only explicitly added inputs are received via UDP.
+    pub fn source(mut self, source: impl ShredSource) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Adds a named shred feed. Once any input is added, `bind_address` is ignored.
+    pub fn input(mut self, name: impl Into<String>, addr: impl Into<String>) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.inputs.push(ShredInputConfig {
+            name: name.into(),
+            bind_address: addr.into(),
+            multicast: None,
+        });
+        self.config = Some(config);
+        self
+    }
+
+    /// Adds a named shred feed that joins `multicast` groups on `addr`
+    pub fn multicast_input(
+        mut self,
+        name: impl Into<String>,
+        addr: impl Into<String>,
+        multicast: MulticastConfig,
+    ) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.inputs.push(ShredInputConfig {
+            name: name.into(),
+            bind_address: addr.into(),
+            multicast: Some(multicast),
+        });
+        self.config = Some(config);
+        self
+    }
+
+    pub fn num_receivers(mut self, num: u8) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.num_receivers = Some(num);
+        self.config = Some(config);
+        self
+    }
+
+    pub fn recv_batch_size(mut self, size: usize) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.recv_batch_size = Some(size);
+        self.config = Some(config);
+        self
+    }
+
+    /// Records every received datagram to rotating files, see `RecordingConfig`
+    pub fn recording(mut self, recording: RecordingConfig) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.recording = Some(recording);
+        self.config = Some(config);
+        self
+    }
+
+    /// Forward received shreds to downstream UDP destinations, see `RelayConfig`
+    pub fn relay(mut self, relay: RelayConfig) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.relay = Some(relay);
+        self.config = Some(config);
+        self
+    }
+
+    /// Drop shreds by sender address, see `SourceFilterConfig`
+    pub fn source_filter(mut self, source_filter: SourceFilterConfig) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.source_filter = Some(source_filter);
+        self.config = Some(config);
+        self
+    }
+
+    /// Drop shreds of other clusters, see `ShredVersionConfig`
+    pub fn shred_version(mut self, shred_version: ShredVersionConfig) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.shred_version = Some(shred_version);
+        self.config = Some(config);
+        self
+    }
+
+    /// Queue capacities and policies between the pipeline stages, see `BackpressureConfig`
+    pub fn backpressure(mut self, backpressure: BackpressureConfig) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.backpressure = backpressure;
+        self.config = Some(config);
+        self
+    }
+
+    /// Drop work on slots further than `max_slot_lag` behind the highest slot with a completed FEC set
+    pub fn max_slot_lag(mut self, max_slot_lag: u64) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.max_slot_lag = Some(max_slot_lag);
+        self.config = Some(config);
+        self
+    }
+
+    /// Slots behind the highest completed slot whose completed FEC sets are remembered
+    pub fn dedup_window_slots(mut self, slots: u64) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.dedup_window_slots = Some(slots);
+        self.config = Some(config);
+        self
+    }
+
+    /// Verify shred signatures against the slot leaders of `leader_schedule`,
+    /// dropping shreds that fail, e.g. with a `JsonLeaderSchedule`
+    pub fn leader_schedule(mut self, leader_schedule: impl LeaderSchedule) -> Self {
+        self.leader_schedule = Some(Arc::new(leader_schedule));
+        self
+    }
+
+    /// Clock for `received_at_micros`, see `TimestampSource`
+    pub fn timestamp_source(mut self, source: TimestampSource) -> Self {
+        let mut config = self.config.unwrap_or_default();
+        config.timestamp_source = source;
+        self.config = Some(config);
+        self
+    }
+
     pub fn num_fec_workers(mut self, num: u8) -> Self {
         let mut config = self.config.unwrap_or_default();
         config.num_fec_workers = Some(num);
@@ -123,6 +316,24 @@ impl<H: TransactionHandler> UnshredProcessorBuilder<H> {
             }
         }
 
-        Ok(UnshredProcessor { handler, config })
+        let fec_channels = FecChannels::new(&config, self.leader_schedule)?;
+
+        Ok(UnshredProcessor {
+            handler,
+            config,
+            sources: self.sources,
+            fec_channels,
+            has_ingest: false,
+        })
+    }
+
+    /// Builds the processor along with a handle to push shreds into it.
+    /// Unless inputs are added explicitly, no UDP socket is opened.
+    pub fn build_with_ingest(self) -> Result<(UnshredProcessor<H>, UnshredIngest)> {
+        let mut processor = self.build()?;
+        processor.has_ingest = true;
+        let ingest = UnshredIngest::new(processor.fec_channels.router.clone());
+
+        Ok((processor, ingest))
     }
 }
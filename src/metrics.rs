@@ -2,7 +2,8 @@ use anyhow::Result;
 #[cfg(feature = "metrics")]
 use prometheus::{
     register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
-    register_int_gauge_vec_with_registry, HistogramVec, IntCounterVec, IntGaugeVec, Registry,
+    register_int_counter_with_registry, register_int_gauge_vec_with_registry, HistogramVec,
+    IntCounter, IntCounterVec, IntGaugeVec, Registry,
 };
 use std::sync::Arc;
 
@@ -11,7 +12,14 @@ pub struct Metrics {
     pub channel_capacity_utilization: IntGaugeVec,
 
     pub receiver_shreds_received: IntCounterVec,
+    pub receiver_packets_received: IntCounterVec,
     pub receiver_socket_buffer_utilization: IntGaugeVec,
+    pub receiver_batch_size: HistogramVec,
+    pub receiver_timestamps: IntCounterVec,
+    pub receiver_shred_version_mismatches: IntCounter,
+
+    pub recorder_shreds: IntCounterVec,
+    pub relay_shreds: IntCounterVec,
 
     pub processor_shreds_accumulated: IntCounterVec,
     pub processor_fec_sets_completed: IntCounterVec,
@@ -19,7 +27,15 @@ pub struct Metrics {
 
     pub processing_latency: HistogramVec,
 
+    pub feed_shreds: IntCounterVec,
+    pub feed_fec_sets_first: IntCounterVec,
+    pub feed_latency_gap: HistogramVec,
+
     pub active_slots: IntGaugeVec,
+    pub stage_dropped: IntCounterVec,
+    pub slot_lag_shed: IntCounterVec,
+    pub processed_fec_sets: IntGaugeVec,
+    pub shred_signatures: IntCounterVec,
 
     pub errors: IntCounterVec,
 }
@@ -44,10 +60,48 @@ impl Metrics {
                 &["type"],
                 registry.clone()
             )?,
+            receiver_packets_received: register_int_counter_vec_with_registry!(
+                "receiver_packets_received_total",
+                "Total packets received per receiver thread",
+                &["receiver"],
+                registry.clone()
+            )?,
             receiver_socket_buffer_utilization: register_int_gauge_vec_with_registry!(
                 "receiver_socket_buffer_utilization_percentage",
-                "Socket buffer utilization percentage",
-                &["type"],
+                "Socket buffer utilization percentage per receiver thread",
+                &["receiver"],
+                registry.clone()
+            )?,
+            receiver_batch_size: register_histogram_vec_with_registry!(
+                "receiver_batch_size",
+                "Datagrams returned per receive syscall",
+                &["receiver"],
+                vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0],
+                registry.clone()
+            )?,
+            receiver_timestamps: register_int_counter_vec_with_registry!(
+                "receiver_timestamps_total",
+                "Datagrams by the clock their received_at was read from",
+                &["receiver", "clock"],
+                registry.clone()
+            )?,
+            // Unlabeled, the version comes from the sender
+            receiver_shred_version_mismatches: register_int_counter_with_registry!(
+                "receiver_shred_version_mismatches_total",
+                "Shreds dropped for an unexpected shred version",
+                registry.clone()
+            )?,
+
+            recorder_shreds: register_int_counter_vec_with_registry!(
+                "recorder_shreds_total",
+                "Datagrams written, failed or dropped by the recorder",
+                &["outcome"],
+                registry.clone()
+            )?,
+            relay_shreds: register_int_counter_vec_with_registry!(
+                "relay_shreds_total",
+                "Shreds forwarded, deduplicated, failed or dropped by the relay",
+                &["outcome"],
                 registry.clone()
             )?,
 
@@ -78,12 +132,56 @@ impl Metrics {
                 registry.clone()
             )?,
 
+            feed_shreds: register_int_counter_vec_with_registry!(
+                "feed_shreds_total",
+                "Shreds per feed by outcome",
+                &["feed", "outcome"], // unique, redundant, late
+                registry.clone()
+            )?,
+            feed_fec_sets_first: register_int_counter_vec_with_registry!(
+                "feed_fec_sets_first_total",
+                "FEC sets for which the feed delivered the first shred",
+                &["feed"],
+                registry.clone()
+            )?,
+            feed_latency_gap: register_histogram_vec_with_registry!(
+                "feed_latency_gap_seconds",
+                "Delay of the feed's first shred behind the fastest feed, per FEC set",
+                &["feed"],
+                vec![0.0, 0.0001, 0.0005, 0.001, 0.002, 0.005, 0.01, 0.05, 0.1],
+                registry.clone()
+            )?,
+
             active_slots: register_int_gauge_vec_with_registry!(
                 "active_slots",
                 "Number of active slots being processed",
                 &["component"],
                 registry.clone()
             )?,
+            stage_dropped: register_int_counter_vec_with_registry!(
+                "stage_dropped_total",
+                "Items dropped by a full queue between pipeline stages",
+                &["stage", "reason"],
+                registry.clone()
+            )?,
+            slot_lag_shed: register_int_counter_vec_with_registry!(
+                "slot_lag_shed_total",
+                "Items dropped for lagging more than max_slot_lag behind the highest slot",
+                &["stage"],
+                registry.clone()
+            )?,
+            processed_fec_sets: register_int_gauge_vec_with_registry!(
+                "processed_fec_sets",
+                "FEC sets and slots in the completed FEC set dedup window",
+                &["type"],
+                registry.clone()
+            )?,
+            shred_signatures: register_int_counter_vec_with_registry!(
+                "shred_signatures_total",
+                "Shred signature verifications by outcome",
+                &["outcome"],
+                registry.clone()
+            )?,
 
             errors: register_int_counter_vec_with_registry!(
                 "errors_total",
@@ -147,6 +245,7 @@ impl IntCounterVec {
         self
     }
     pub fn inc(&self) {}
+    pub fn inc_by(&self, _: u64) {}
 }
 
 #[cfg(not(feature = "metrics"))]
@@ -0,0 +1,799 @@
+use crate::{
+    replay::{Pacer, ReplayMode},
+    source::{ShredSource, SourceShred},
+};
+
+use anyhow::Result;
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::Range,
+    path::Path,
+};
+use tracing::{info, warn};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+// Link types
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const IPPROTO_UDP: u8 = 17;
+
+/// Caps the buffer for a corrupt length field, well above any jumbo frame
+const MAX_BLOCK_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct PcapNgInterface {
+    linktype: u32,
+    units_per_second: u64,
+}
+
+enum Format {
+    Pcap {
+        big_endian: bool,
+        units_per_second: u64,
+        linktype: u32,
+    },
+    PcapNg {
+        big_endian: bool,
+        interfaces: Vec<PcapNgInterface>,
+    },
+}
+
+/// UDP payloads of a pcap or pcapng capture (e.g. from `tcpdump -w`), with the
+/// capture timestamps as `received_at_micros` and the UDP sender as `peer_addr`.
+///
+/// Supports Ethernet (with VLAN tags), Linux cooked (SLL, SLL2), loopback and raw IP
+/// link types. Fragmented datagrams are skipped. Never drops shreds on backpressure.
+pub struct PcapSource {
+    name: String,
+    reader: BufReader<File>,
+    format: Format,
+    dst_port: Option<u16>,
+    mode: ReplayMode,
+    pacer: Pacer,
+    block: Vec<u8>,
+}
+
+impl PcapSource {
+    /// `dst_port` - only UDP datagrams sent to this port, all UDP datagrams if `None`
+    pub fn open(path: impl AsRef<Path>, dst_port: Option<u16>, mode: ReplayMode) -> Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let format = if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+            // Endianness is only known once the section header is read
+            Format::PcapNg {
+                big_endian: false,
+                interfaces: Vec::new(),
+            }
+        } else {
+            let (big_endian, units_per_second) =
+                match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                    (PCAP_MAGIC_MICROS, _) => (false, 1_000_000),
+                    (PCAP_MAGIC_NANOS, _) => (false, 1_000_000_000),
+                    (_, PCAP_MAGIC_MICROS) => (true, 1_000_000),
+                    (_, PCAP_MAGIC_NANOS) => (true, 1_000_000_000),
+                    _ => return Err(anyhow::anyhow!("{} is not a pcap file", path.display())),
+                };
+            // version, thiszone, sigfigs, snaplen, network
+            let mut header = [0u8; 20];
+            reader.read_exact(&mut header)?;
+            Format::Pcap {
+                big_endian,
+                units_per_second,
+                // Upper bits may carry FCS information
+                linktype: read_u32(&header[16..20], big_endian) & 0x0fff_ffff,
+            }
+        };
+
+        let mut source = Self {
+            name: "pcap".to_string(),
+            reader,
+            format,
+            dst_port,
+            mode,
+            pacer: Pacer::default(),
+            block: Vec::with_capacity(2048),
+        };
+        if let Format::PcapNg { .. } = source.format {
+            source.read_pcapng_block_body(PCAPNG_SECTION_HEADER)?;
+        }
+        info!("Reading capture {}", path.display());
+
+        Ok(source)
+    }
+
+    /// Feed name of the capture's shreds, `pcap` by default
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Reads the next captured packet into `block`
+    /// # Returns
+    /// * `Some((linktype, timestamp_micros, packet range in block))`, `None` at the end
+    fn next_packet(&mut self) -> Result<Option<(u32, u64, Range<usize>)>> {
+        match self.format {
+            Format::Pcap {
+                big_endian,
+                units_per_second,
+                linktype,
+            } => {
+                let mut header = [0u8; 16];
+                if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let seconds = read_u32(&header[0..4], big_endian) as u64;
+                let fraction = read_u32(&header[4..8], big_endian) as u64;
+                let captured_len = read_u32(&header[8..12], big_endian) as usize;
+
+                self.read_block(captured_len)?;
+                let micros = seconds * 1_000_000 + fraction * 1_000_000 / units_per_second;
+                Ok(Some((linktype, micros, 0..captured_len)))
+            }
+            Format::PcapNg { .. } => loop {
+                let mut block_type = [0u8; 4];
+                if !read_exact_or_eof(&mut self.reader, &mut block_type)? {
+                    return Ok(None);
+                }
+                // The section header type reads the same in either byte order
+                let block_type = match self.format {
+                    Format::PcapNg {
+                        big_endian: true, ..
+                    } => u32::from_be_bytes(block_type),
+                    _ => u32::from_le_bytes(block_type),
+                };
+
+                if let Some(packet) = self.read_pcapng_block_body(block_type)? {
+                    return Ok(Some(packet));
+                }
+            },
+        }
+    }
+
+    /// Reads the rest of a pcapng block, returning the packet of enhanced packet blocks
+    fn read_pcapng_block_body(
+        &mut self,
+        block_type: u32,
+    ) -> Result<Option<(u32, u64, Range<usize>)>> {
+        let mut length = [0u8; 4];
+        self.reader.read_exact(&mut length)?;
+
+        if block_type == PCAPNG_SECTION_HEADER {
+            // A new section may switch endianness and starts without interfaces
+            let mut byte_order = [0u8; 4];
+            self.reader.read_exact(&mut byte_order)?;
+            let big_endian = match u32::from_le_bytes(byte_order) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return Err(anyhow::anyhow!("Invalid pcapng byte order magic")),
+            };
+            self.format = Format::PcapNg {
+                big_endian,
+                interfaces: Vec::new(),
+            };
+
+            let length = read_u32(&length, big_endian) as usize;
+            // Type, length and byte order magic are read, the rest is skipped
+            self.read_block(length.saturating_sub(12))?;
+            return Ok(None);
+        }
+
+        let Format::PcapNg {
+            big_endian,
+            interfaces,
+        } = &mut self.format
+        else {
+            return Ok(None);
+        };
+        let big_endian = *big_endian;
+        let length = read_u32(&length, big_endian) as usize;
+        if length < 12 || !length.is_multiple_of(4) {
+            return Err(anyhow::anyhow!("Invalid pcapng block length {}", length));
+        }
+        // Body and the trailing length
+        let body_len = length - 8;
+        if body_len > MAX_BLOCK_SIZE {
+            return Err(anyhow::anyhow!("Invalid pcapng block length {}", length));
+        }
+        self.block.resize(body_len, 0);
+        self.reader.read_exact(&mut self.block)?;
+        let body = &self.block[..body_len - 4];
+
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                let linktype = read_u16(&body[0..2], big_endian) as u32;
+                let units_per_second = pcapng_units_per_second(&body[8..], big_endian);
+                interfaces.push(PcapNgInterface {
+                    linktype,
+                    units_per_second,
+                });
+                Ok(None)
+            }
+            PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                let interface_id = read_u32(&body[0..4], big_endian) as usize;
+                let Some(interface) = interfaces.get(interface_id) else {
+                    warn!("Packet for unknown pcapng interface {}", interface_id);
+                    return Ok(None);
+                };
+                let timestamp = (read_u32(&body[4..8], big_endian) as u64) << 32
+                    | read_u32(&body[8..12], big_endian) as u64;
+                let captured_len = read_u32(&body[12..16], big_endian) as usize;
+                if 20 + captured_len > body.len() {
+                    return Err(anyhow::anyhow!("Truncated pcapng packet block"));
+                }
+
+                let micros =
+                    (timestamp as u128 * 1_000_000 / interface.units_per_second as u128) as u64;
+                Ok(Some((interface.linktype, micros, 20..20 + captured_len)))
+            }
+            // Simple packet blocks have no timestamp, other blocks carry no packets
+            _ => Ok(None),
+        }
+    }
+
+    fn read_block(&mut self, len: usize) -> Result<()> {
+        if len > MAX_BLOCK_SIZE {
+            return Err(anyhow::anyhow!("Invalid capture record length {}", len));
+        }
+        self.block.resize(len, 0);
+        self.reader.read_exact(&mut self.block)?;
+        Ok(())
+    }
+}
+
+impl ShredSource for PcapSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_shred(&mut self) -> Result<Option<SourceShred<'_>>> {
+        loop {
+            let Some((linktype, received_at_micros, packet)) = self.next_packet()? else {
+                return Ok(None);
+            };
+            let Some(datagram) = parse_udp(linktype, &self.block[packet.clone()]) else {
+                continue;
+            };
+            if self.dst_port.is_some_and(|port| port != datagram.dst_port) {
+                continue;
+            }
+
+            if self.mode == ReplayMode::Paced {
+                self.pacer.wait(received_at_micros);
+            }
+
+            let payload =
+                packet.start + datagram.payload.start..packet.start + datagram.payload.end;
+            return Ok(Some(SourceShred {
+                bytes: &self.block[payload],
+                received_at_micros: Some(received_at_micros),
+                peer_addr: Some(datagram.src),
+                feed: None,
+            }));
+        }
+    }
+
+    fn wait_for_capacity(&self) -> bool {
+        true
+    }
+}
+
+struct UdpDatagram {
+    src: SocketAddr,
+    dst_port: u16,
+    payload: Range<usize>,
+}
+
+/// Locates the UDP payload in a captured frame, `None` for anything else
+fn parse_udp(linktype: u32, frame: &[u8]) -> Option<UdpDatagram> {
+    let ip_start = match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                offset += 4;
+                ethertype = u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?);
+            }
+            if ethertype != ETHERTYPE_IPV4 && ethertype != ETHERTYPE_IPV6 {
+                return None;
+            }
+            offset + 2
+        }
+        LINKTYPE_LINUX_SLL => 16,
+        LINKTYPE_LINUX_SLL2 => 20,
+        LINKTYPE_NULL | LINKTYPE_LOOP => 4,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => 0,
+        _ => return None,
+    };
+    let ip = frame.get(ip_start..)?;
+
+    let (src_ip, udp_start) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            let protocol = *ip.get(9)?;
+            let fragment = u16::from_be_bytes(ip.get(6..8)?.try_into().ok()?);
+            // More fragments flag or a fragment offset
+            if protocol != IPPROTO_UDP || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            (IpAddr::V4(Ipv4Addr::from(src)), header_len)
+        }
+        6 => {
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let mut next_header = *ip.get(6)?;
+            let mut offset = 40;
+            // Hop-by-hop, routing and destination options headers
+            while matches!(next_header, 0 | 43 | 60) {
+                let header = ip.get(offset..offset + 2)?;
+                next_header = header[0];
+                offset += (header[1] as usize + 1) * 8;
+            }
+            if next_header != IPPROTO_UDP {
+                return None;
+            }
+            (IpAddr::V6(Ipv6Addr::from(src)), offset)
+        }
+        _ => return None,
+    };
+
+    let udp = ip.get(udp_start..udp_start + 8)?;
+    let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+    let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+    let udp_len = u16::from_be_bytes([udp[4], udp[5]]) as usize;
+    let payload_start = ip_start + udp_start + 8;
+    // Snapped captures end before the datagram does
+    let payload_end = std::cmp::min(payload_start + udp_len.checked_sub(8)?, frame.len());
+
+    Some(UdpDatagram {
+        src: SocketAddr::new(src_ip, src_port),
+        dst_port,
+        payload: payload_start..payload_end,
+    })
+}
+
+/// `if_tsresol` of an interface description's options, microseconds by default
+fn pcapng_units_per_second(mut options: &[u8], big_endian: bool) -> u64 {
+    while options.len() >= 4 {
+        let code = read_u16(&options[0..2], big_endian);
+        let len = read_u16(&options[2..4], big_endian) as usize;
+        let Some(value) = options.get(4..4 + len) else {
+            break;
+        };
+
+        if code == PCAPNG_OPTION_TSRESOL && len >= 1 {
+            let exponent = (value[0] & 0x7f) as u32;
+            return if value[0] & 0x80 == 0 {
+                10u64.checked_pow(exponent).unwrap_or(1_000_000)
+            } else {
+                1u64.checked_shl(exponent).unwrap_or(1_000_000)
+            };
+        }
+
+        // Options are padded to 32 bits
+        options = options.get(4 + len.div_ceil(4) * 4..).unwrap_or(&[]);
+    }
+
+    1_000_000
+}
+
+/// Fills `buffer`, `false` if the reader was already at its end
+fn read_exact_or_eof(reader: &mut impl Read, buffer: &mut [u8]) -> Result<bool> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 if read == 0 => return Ok(false),
+            0 => return Err(anyhow::anyhow!("Capture ends with a partial record")),
+            n => read += n,
+        }
+    }
+
+    Ok(true)
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const PAYLOAD: &[u8] = b"shred payload";
+
+    fn ipv4_udp(src: [u8; 4], src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let udp_len = 8 + payload.len() as u16;
+        let mut packet = vec![0x45, 0];
+        packet.extend_from_slice(&(20 + udp_len).to_be_bytes());
+        // id, flags and fragment offset, ttl, protocol, checksum
+        packet.extend_from_slice(&[0, 0, 0, 0, 64, IPPROTO_UDP, 0, 0]);
+        packet.extend_from_slice(&src);
+        packet.extend_from_slice(&[10, 0, 0, 2]);
+        packet.extend(udp(src_port, dst_port, payload));
+        packet
+    }
+
+    /// With a hop-by-hop options header before the UDP header
+    fn ipv6_udp(src: Ipv6Addr, src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let udp_len = 8 + payload.len() as u16;
+        let mut packet = vec![0x60, 0, 0, 0];
+        packet.extend_from_slice(&(8 + udp_len).to_be_bytes());
+        // Next header hop-by-hop, hop limit
+        packet.extend_from_slice(&[0, 64]);
+        packet.extend_from_slice(&src.octets());
+        packet.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        packet.extend_from_slice(&[IPPROTO_UDP, 0, 1, 4, 0, 0, 0, 0]);
+        packet.extend(udp(src_port, dst_port, payload));
+        packet
+    }
+
+    fn udp(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = Vec::new();
+        datagram.extend_from_slice(&src_port.to_be_bytes());
+        datagram.extend_from_slice(&dst_port.to_be_bytes());
+        datagram.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(payload);
+        datagram
+    }
+
+    fn ethernet(vlan_tags: &[u16], ethertype: u16, ip: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        for tpid in vlan_tags {
+            frame.extend_from_slice(&tpid.to_be_bytes());
+            frame.extend_from_slice(&100u16.to_be_bytes());
+        }
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(ip);
+        frame
+    }
+
+    fn payload<'a>(frame: &'a [u8], datagram: &UdpDatagram) -> &'a [u8] {
+        &frame[datagram.payload.clone()]
+    }
+
+    #[test]
+    fn parse_udp_ethernet_ipv4() {
+        let frame = ethernet(
+            &[],
+            ETHERTYPE_IPV4,
+            &ipv4_udp([10, 0, 0, 1], 8001, 9000, PAYLOAD),
+        );
+        let datagram = parse_udp(LINKTYPE_ETHERNET, &frame).unwrap();
+
+        assert_eq!(datagram.src, "10.0.0.1:8001".parse().unwrap());
+        assert_eq!(datagram.dst_port, 9000);
+        assert_eq!(payload(&frame, &datagram), PAYLOAD);
+    }
+
+    #[test]
+    fn parse_udp_vlan_tags() {
+        let ip = ipv4_udp([10, 0, 0, 1], 8001, 9000, PAYLOAD);
+        for tags in [&[ETHERTYPE_VLAN][..], &[ETHERTYPE_QINQ, ETHERTYPE_VLAN]] {
+            let frame = ethernet(tags, ETHERTYPE_IPV4, &ip);
+            let datagram = parse_udp(LINKTYPE_ETHERNET, &frame).unwrap();
+            assert_eq!(datagram.dst_port, 9000);
+            assert_eq!(payload(&frame, &datagram), PAYLOAD);
+        }
+    }
+
+    #[test]
+    fn parse_udp_ipv6_extension_header() {
+        let src: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let frame = ethernet(
+            &[ETHERTYPE_VLAN],
+            ETHERTYPE_IPV6,
+            &ipv6_udp(src, 8001, 9000, PAYLOAD),
+        );
+        let datagram = parse_udp(LINKTYPE_ETHERNET, &frame).unwrap();
+
+        assert_eq!(datagram.src, SocketAddr::new(src.into(), 8001));
+        assert_eq!(payload(&frame, &datagram), PAYLOAD);
+    }
+
+    #[test]
+    fn parse_udp_cooked_and_raw_link_types() {
+        let ip = ipv4_udp([10, 0, 0, 1], 8001, 9000, PAYLOAD);
+        for (linktype, header_len) in [
+            (LINKTYPE_LINUX_SLL, 16),
+            (LINKTYPE_LINUX_SLL2, 20),
+            (LINKTYPE_NULL, 4),
+            (LINKTYPE_RAW, 0),
+        ] {
+            let mut frame = vec![0u8; header_len];
+            frame.extend_from_slice(&ip);
+            let datagram = parse_udp(linktype, &frame).unwrap();
+            assert_eq!(payload(&frame, &datagram), PAYLOAD);
+        }
+    }
+
+    #[test]
+    fn parse_udp_skips_other_packets() {
+        let ip = ipv4_udp([10, 0, 0, 1], 8001, 9000, PAYLOAD);
+
+        let mut fragment = ip.clone();
+        fragment[6] = 0x20; // More fragments
+        assert!(parse_udp(LINKTYPE_RAW, &fragment).is_none());
+
+        let mut tcp = ip.clone();
+        tcp[9] = 6;
+        assert!(parse_udp(LINKTYPE_RAW, &tcp).is_none());
+
+        let arp = ethernet(&[], 0x0806, &ip);
+        assert!(parse_udp(LINKTYPE_ETHERNET, &arp).is_none());
+        assert!(parse_udp(LINKTYPE_RAW, &ip[..24]).is_none());
+    }
+
+    #[test]
+    fn parse_udp_snapped_capture() {
+        let ip = ipv4_udp([10, 0, 0, 1], 8001, 9000, PAYLOAD);
+        let snapped = &ip[..ip.len() - 4];
+        let datagram = parse_udp(LINKTYPE_RAW, snapped).unwrap();
+
+        assert_eq!(payload(snapped, &datagram), &PAYLOAD[..PAYLOAD.len() - 4]);
+    }
+
+    fn tsresol_option(value: u8, big_endian: bool) -> Vec<u8> {
+        let mut option = Vec::new();
+        option.extend(u16_bytes(PCAPNG_OPTION_TSRESOL, big_endian));
+        option.extend(u16_bytes(1, big_endian));
+        option.extend_from_slice(&[value, 0, 0, 0]);
+        option
+    }
+
+    #[test]
+    fn pcapng_tsresol() {
+        assert_eq!(pcapng_units_per_second(&[], false), 1_000_000);
+        assert_eq!(
+            pcapng_units_per_second(&tsresol_option(9, false), false),
+            1_000_000_000
+        );
+        assert_eq!(
+            pcapng_units_per_second(&tsresol_option(9, true), true),
+            1_000_000_000
+        );
+        // Negative power of two
+        assert_eq!(
+            pcapng_units_per_second(&tsresol_option(0x80 | 20, false), false),
+            1 << 20
+        );
+
+        // After a padded comment option
+        let mut options = Vec::new();
+        options.extend(u16_bytes(1, false));
+        options.extend(u16_bytes(5, false));
+        options.extend_from_slice(b"hello\0\0\0");
+        options.extend(tsresol_option(3, false));
+        assert_eq!(pcapng_units_per_second(&options, false), 1_000);
+    }
+
+    fn u16_bytes(value: u16, big_endian: bool) -> [u8; 2] {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    fn u32_bytes(value: u32, big_endian: bool) -> [u8; 4] {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    /// Classic pcap capture of `(seconds, fraction, frame)` packets
+    fn pcap(big_endian: bool, magic: u32, linktype: u32, packets: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut capture = Vec::new();
+        capture.extend(u32_bytes(magic, big_endian));
+        capture.extend(u16_bytes(2, big_endian));
+        capture.extend(u16_bytes(4, big_endian));
+        capture.extend_from_slice(&[0; 8]); // thiszone, sigfigs
+        capture.extend(u32_bytes(65535, big_endian));
+        capture.extend(u32_bytes(linktype, big_endian));
+        for (seconds, fraction, frame) in packets {
+            capture.extend(u32_bytes(*seconds, big_endian));
+            capture.extend(u32_bytes(*fraction, big_endian));
+            capture.extend(u32_bytes(frame.len() as u32, big_endian));
+            capture.extend(u32_bytes(frame.len() as u32, big_endian));
+            capture.extend_from_slice(frame);
+        }
+        capture
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8], big_endian: bool) -> Vec<u8> {
+        let padded = body.len().div_ceil(4) * 4;
+        let length = (12 + padded) as u32;
+        let mut block = Vec::new();
+        block.extend(u32_bytes(block_type, big_endian));
+        block.extend(u32_bytes(length, big_endian));
+        block.extend_from_slice(body);
+        block.resize(8 + padded, 0);
+        block.extend(u32_bytes(length, big_endian));
+        block
+    }
+
+    /// pcapng section with one interface and `(timestamp, frame)` packets
+    fn pcapng_section(
+        big_endian: bool,
+        linktype: u16,
+        tsresol: Option<u8>,
+        packets: &[(u64, &[u8])],
+    ) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(u32_bytes(PCAPNG_BYTE_ORDER_MAGIC, big_endian));
+        header.extend(u16_bytes(1, big_endian));
+        header.extend(u16_bytes(0, big_endian));
+        header.extend_from_slice(&[0xff; 8]); // Unknown section length
+        let mut section = pcapng_block(PCAPNG_SECTION_HEADER, &header, big_endian);
+
+        let mut interface = Vec::new();
+        interface.extend(u16_bytes(linktype, big_endian));
+        interface.extend_from_slice(&[0, 0]);
+        interface.extend(u32_bytes(65535, big_endian));
+        if let Some(tsresol) = tsresol {
+            interface.extend(tsresol_option(tsresol, big_endian));
+        }
+        section.extend(pcapng_block(
+            PCAPNG_INTERFACE_DESCRIPTION,
+            &interface,
+            big_endian,
+        ));
+
+        for (timestamp, frame) in packets {
+            let mut packet = Vec::new();
+            packet.extend(u32_bytes(0, big_endian));
+            packet.extend(u32_bytes((timestamp >> 32) as u32, big_endian));
+            packet.extend(u32_bytes(*timestamp as u32, big_endian));
+            packet.extend(u32_bytes(frame.len() as u32, big_endian));
+            packet.extend(u32_bytes(frame.len() as u32, big_endian));
+            packet.extend_from_slice(frame);
+            section.extend(pcapng_block(PCAPNG_ENHANCED_PACKET, &packet, big_endian));
+        }
+        section
+    }
+
+    type Replayed = (Vec<u8>, Option<u64>, Option<SocketAddr>);
+
+    fn replay(name: &str, capture: &[u8], dst_port: Option<u16>) -> Vec<Replayed> {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("unshred-{}-{}.pcap", name, std::process::id()));
+        std::fs::write(&path, capture).unwrap();
+        let mut source = PcapSource::open(&path, dst_port, ReplayMode::AsFastAsPossible).unwrap();
+
+        let mut shreds = Vec::new();
+        while let Some(shred) = source.next_shred().unwrap() {
+            shreds.push((
+                shred.bytes.to_vec(),
+                shred.received_at_micros,
+                shred.peer_addr,
+            ));
+        }
+        std::fs::remove_file(&path).unwrap();
+        shreds
+    }
+
+    #[test]
+    fn pcap_byte_orders() {
+        let first = ethernet(
+            &[],
+            ETHERTYPE_IPV4,
+            &ipv4_udp([10, 0, 0, 1], 8001, 9000, b"first"),
+        );
+        let other_port = ethernet(
+            &[],
+            ETHERTYPE_IPV4,
+            &ipv4_udp([10, 0, 0, 1], 8001, 9001, b"other"),
+        );
+        let second = ethernet(
+            &[ETHERTYPE_VLAN],
+            ETHERTYPE_IPV4,
+            &ipv4_udp([10, 0, 0, 3], 8003, 9000, b"second"),
+        );
+        let packets = [
+            (1, 500, &first[..]),
+            (1, 600, &other_port[..]),
+            (2, 0, &second[..]),
+        ];
+        let peer = |ip: [u8; 4], port| Some(SocketAddr::from((ip, port)));
+
+        for big_endian in [false, true] {
+            let capture = pcap(big_endian, PCAP_MAGIC_MICROS, LINKTYPE_ETHERNET, &packets);
+            assert_eq!(
+                replay("micros", &capture, Some(9000)),
+                vec![
+                    (
+                        b"first".to_vec(),
+                        Some(1_000_500),
+                        peer([10, 0, 0, 1], 8001)
+                    ),
+                    (
+                        b"second".to_vec(),
+                        Some(2_000_000),
+                        peer([10, 0, 0, 3], 8003)
+                    ),
+                ]
+            );
+
+            let capture = pcap(big_endian, PCAP_MAGIC_NANOS, LINKTYPE_ETHERNET, &packets);
+            let shreds = replay("nanos", &capture, None);
+            assert_eq!(shreds.len(), 3);
+            assert_eq!(shreds[0].1, Some(1_000_000));
+        }
+    }
+
+    #[test]
+    fn pcapng_sections() {
+        let src: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let v6 = ipv6_udp(src, 8001, 9000, b"v6");
+        let v4 = ethernet(
+            &[ETHERTYPE_VLAN],
+            ETHERTYPE_IPV4,
+            &ipv4_udp([10, 0, 0, 1], 8001, 9000, b"v4"),
+        );
+
+        // Nanosecond big endian section, then a default microsecond little endian one
+        let mut capture =
+            pcapng_section(true, LINKTYPE_RAW as u16, Some(9), &[(3_000_000_500, &v6)]);
+        capture.extend(pcapng_section(
+            false,
+            LINKTYPE_ETHERNET as u16,
+            None,
+            &[(4_000_001, &v4)],
+        ));
+
+        assert_eq!(
+            replay("pcapng", &capture, Some(9000)),
+            vec![
+                (
+                    b"v6".to_vec(),
+                    Some(3_000_000),
+                    Some(SocketAddr::new(src.into(), 8001))
+                ),
+                (
+                    b"v4".to_vec(),
+                    Some(4_000_001),
+                    Some(SocketAddr::from(([10, 0, 0, 1], 8001)))
+                ),
+            ]
+        );
+    }
+}
@@ -1,40 +1,141 @@
 #[cfg(feature = "metrics")]
 use crate::metrics::Metrics;
-use crate::{types::ShredBytesMeta, TransactionEvent, TransactionHandler, UnshredConfig};
+use crate::{
+    coverage::{EvictedSlot, IncompleteFecSets, IncompleteSlots},
+    dedup::ProcessedFecSets,
+    duplicate::{DuplicateDetector, DuplicateSlots},
+    events::{
+        DuplicateSlotEvent, IncompleteFecSet, MerkleChainStatus, SlotCompleteEvent, SlotEvent,
+    },
+    filter::SourceFilter,
+    header::{DATA_OFFSET_PAYLOAD, OFFSET_FLAGS, OFFSET_NUM_DATA, OFFSET_SIZE, SIGNATURE_LEN},
+    leader::{LeaderSchedule, SignatureVerifier},
+    race::{FecSetRace, LateShreds, SlotRace},
+    receiver::ShredReceiver,
+    recording::Recorder,
+    relay::Relay,
+    source::{run_source, RouterHooks, ShredRouter, ShredSource},
+    stage::{self, SlotItem, StageReceiver, StageSender},
+    tip::SlotTip,
+    types::ShredBytesMeta,
+    version::ShredVersionCheck,
+    BackpressurePolicy, ShredInputConfig, TransactionEvent, TransactionHandler, UnshredConfig,
+};
 
-use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+#[cfg(feature = "metrics")]
+use ahash::HashSet;
+use ahash::{HashMap, HashMapExt};
 use anyhow::Result;
-use dashmap::DashSet;
 use solana_entry::entry::Entry;
+use solana_hash::Hash;
 use solana_ledger::shred::{ReedSolomonCache, Shred, ShredType};
 use std::{
+    collections::{BTreeMap, BTreeSet},
     io::Cursor,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        OnceLock,
+    },
     time::{Instant, SystemTime, UNIX_EPOCH},
     u64,
 };
 use std::{sync::Arc, time::Duration};
 use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::{error, info, warn};
-
-// Header offsets
-const OFFSET_FLAGS: usize = 85;
-const OFFSET_SIZE: usize = 86; // Payload total size offset
-const DATA_OFFSET_PAYLOAD: usize = 88;
+use tracing::{error, info, trace, warn};
+
+// Data flags
+const DATA_COMPLETE_SHRED: u8 = 0x40;
+const LAST_SHRED_IN_SLOT: u8 = 0xC0;
+// Merkle roots accumulated per FEC set, the genuine one and equivocation fit in
+const MAX_BUCKETS_PER_FEC_SET: usize = 4;
+// Merkle roots per FEC set remembered as failing signature verification
+const MAX_REJECTED_ROOTS_PER_FEC_SET: usize = 16;
+// Dispatcher cleanup and evicted slot reports
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+// Completed slots remembered after their eviction, so late FEC sets do not complete them again
+const MAX_COMPLETED_SLOTS: usize = 512;
 
 #[derive(Debug, Clone)]
 pub struct CompletedFecSet {
     pub slot: u64,
+    pub fec_set_index: u32,
+    pub merkle_root: Hash,
+    /// Merkle root of the previous FEC set, `None` for unchained shreds
+    pub chained_merkle_root: Option<Hash>,
     pub data_shreds: HashMap<u32, ShredMeta>,
+    /// Data shreds recovered from code shreds
+    pub recovered_shreds: u32,
+    pub race: FecSetRace,
+}
+
+impl SlotItem for CompletedFecSet {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
 }
 
+/// Shreds of one FEC set sharing one merkle root
 struct FecSetAccumulator {
     slot: u64,
+    merkle_root: Hash,
     data_shreds: HashMap<u32, ShredMeta>,
     code_shreds: HashMap<u32, ShredMeta>,
     expected_data_shreds: Option<usize>,
+    recovered_shreds: u32,
+    race: FecSetRace,
     created_at: Instant,
 }
 
+/// Buckets of one FEC set, one per merkle root
+struct FecSetBuckets {
+    buckets: Vec<FecSetAccumulator>,
+    // Roots whose signature failed verification, their shreds are dropped unverified
+    rejected_roots: Vec<Hash>,
+    created_at: Instant,
+}
+
+impl FecSetBuckets {
+    fn new() -> Self {
+        Self {
+            buckets: Vec::new(),
+            rejected_roots: Vec::new(),
+            created_at: Instant::now(),
+        }
+    }
+
+    fn position(&self, merkle_root: &Hash) -> Option<usize> {
+        self.buckets
+            .iter()
+            .position(|acc| acc.merkle_root == *merkle_root)
+    }
+
+    fn reject(&mut self, merkle_root: Hash) {
+        if self.rejected_roots.len() >= MAX_REJECTED_ROOTS_PER_FEC_SET {
+            self.rejected_roots.remove(0);
+        }
+        self.rejected_roots.push(merkle_root);
+    }
+
+    /// Drops the bucket with the fewest shreds, the newest of those, so that roots
+    /// spoofed into the FEC set cannot crowd out the one accumulating the genuine shreds
+    fn evict_smallest(&mut self) {
+        let smallest = self
+            .buckets
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, acc)| {
+                (
+                    acc.data_shreds.len() + acc.code_shreds.len(),
+                    std::cmp::Reverse(acc.created_at),
+                )
+            })
+            .map(|(bucket, _)| bucket);
+        if let Some(bucket) = smallest {
+            self.buckets.swap_remove(bucket);
+        }
+    }
+}
+
 #[derive(Debug)]
 enum ReconstructionStatus {
     NotReady,
@@ -48,6 +149,24 @@ pub struct BatchWork {
     pub batch_start_idx: u32,
     pub batch_end_idx: u32,
     pub shreds: HashMap<u32, ShredMeta>,
+    pub progress: Arc<SlotProgress>,
+    /// Set once the batch is decoded, a batch dropped before counts as failed
+    pub decoded: bool,
+}
+
+impl SlotItem for BatchWork {
+    fn slot(&self) -> u64 {
+        self.slot
+    }
+}
+
+impl Drop for BatchWork {
+    fn drop(&mut self) {
+        // Shed, dropped by backpressure or failed to decode
+        if !self.decoded {
+            self.progress.fail_batch();
+        }
+    }
 }
 
 struct CombinedDataMeta {
@@ -68,12 +187,283 @@ pub struct EntryMeta {
     pub received_at_micros: Option<u64>,
 }
 
+/// Merkle roots of a completed FEC set
+#[derive(Debug, Clone, Copy)]
+struct ChainLink {
+    merkle_root: Hash,
+    chained_merkle_root: Option<Hash>,
+    next_fec_set_index: u32,
+}
+
+/// Links between the completed FEC sets of a slot, by fec set index
+#[derive(Debug, Default)]
+struct MerkleChain {
+    links: BTreeMap<u32, ChainLink>,
+    broken: bool,
+    // A checked link had no chained merkle root
+    unknown: bool,
+}
+
+impl MerkleChain {
+    /// Adds the link of a completed FEC set and checks it against its neighbours.
+    /// Links to FEC sets not completed yet are checked once those arrive.
+    fn add(&mut self, fec_set_index: u32, link: ChainLink) {
+        let previous = self
+            .links
+            .range(..fec_set_index)
+            .next_back()
+            .map(|(_, previous)| *previous)
+            .filter(|previous| previous.next_fec_set_index == fec_set_index);
+        if let Some(previous) = previous {
+            self.check(&previous, &link);
+        }
+        if let Some(next) = self.links.get(&link.next_fec_set_index).copied() {
+            self.check(&link, &next);
+        }
+        self.links.insert(fec_set_index, link);
+    }
+
+    fn check(&mut self, previous: &ChainLink, next: &ChainLink) {
+        match next.chained_merkle_root {
+            Some(chained_merkle_root) => self.broken |= chained_merkle_root != previous.merkle_root,
+            None => self.unknown = true,
+        }
+    }
+
+    fn merkle_root(&self, fec_set_index: u32) -> Option<Hash> {
+        self.links.get(&fec_set_index).map(|link| link.merkle_root)
+    }
+
+    /// Verdict over the links checked so far, final once every FEC set of the slot completed
+    fn status(&self) -> MerkleChainStatus {
+        if self.broken {
+            MerkleChainStatus::Broken
+        } else if self.unknown {
+            MerkleChainStatus::Unknown
+        } else {
+            MerkleChainStatus::Verified
+        }
+    }
+}
+
+/// Decoding progress of a slot, shared by the dispatcher and the batch workers.
+/// The dispatcher holds one pending count until the slot is complete, every dispatched
+/// batch holds one until it is decoded or dropped. Whoever brings the pending count
+/// to zero emits the `SlotCompleteEvent`, unless a batch was dropped.
+#[derive(Debug)]
+pub struct SlotProgress {
+    pending: AtomicU32,
+    entries: AtomicU32,
+    transactions: AtomicU32,
+    failed_batches: AtomicU32,
+    // Set by the dispatcher once the last batch is dispatched
+    complete: OnceLock<SlotCompleteEvent>,
+    // Set once the `SlotCompleteEvent` is emitted
+    decoded: AtomicBool,
+}
+
+impl SlotProgress {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: AtomicU32::new(1),
+            entries: AtomicU32::new(0),
+            transactions: AtomicU32::new(0),
+            failed_batches: AtomicU32::new(0),
+            complete: OnceLock::new(),
+            decoded: AtomicBool::new(false),
+        }
+    }
+
+    /// Releases one pending count, returns the event if it was the last one and every batch was decoded
+    fn release(&self) -> Option<SlotCompleteEvent> {
+        if self.pending.fetch_sub(1, Ordering::AcqRel) != 1
+            || self.failed_batches.load(Ordering::Acquire) > 0
+        {
+            return None;
+        }
+        let mut event = self.complete.get()?.clone();
+        event.entries = self.entries.load(Ordering::Relaxed);
+        event.transactions = self.transactions.load(Ordering::Relaxed);
+        self.decoded.store(true, Ordering::Release);
+        Some(event)
+    }
+
+    /// Releases the pending count of a batch that was not decoded, the slot will not complete
+    fn fail_batch(&self) {
+        self.failed_batches.fetch_add(1, Ordering::AcqRel);
+        let _ = self.release();
+    }
+
+    /// Whether no batch of a fully dispatched slot is still decoding
+    pub(crate) fn settled(&self) -> bool {
+        self.complete.get().is_none() || self.pending.load(Ordering::Acquire) == 0
+    }
+
+    /// Whether the slot completed and every batch of it was decoded
+    pub(crate) fn decoded(&self) -> bool {
+        self.decoded.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn transactions(&self) -> u32 {
+        self.transactions.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn failed_batches(&self) -> u32 {
+        self.failed_batches.load(Ordering::Relaxed)
+    }
+}
+
 pub struct SlotAccumulator {
     data_shreds: HashMap<u32, ShredMeta>, // index -> shred
     last_processed_batch_idx: Option<u32>,
+    last_shred_idx: Option<u32>,
+    recovered_shreds: u32,
+    progress: Arc<SlotProgress>,
+    merkle_chain: MerkleChain,
+    // Conflicting FEC sets arrived, no batch is dispatched from then on
+    equivocated: bool,
+    race: SlotRace,
     created_at: Instant,
 }
 
+/// Most recent completed slots, kept past the eviction of their accumulators
+#[derive(Debug, Default)]
+struct CompletedSlots {
+    slots: BTreeSet<u64>,
+}
+
+impl CompletedSlots {
+    fn insert(&mut self, slot: u64) {
+        self.slots.insert(slot);
+        if self.slots.len() > MAX_COMPLETED_SLOTS {
+            self.slots.pop_first();
+        }
+    }
+
+    fn contains(&self, slot: u64) -> bool {
+        self.slots.contains(&slot)
+    }
+}
+
+/// Receiver -> fec worker channels and the dedup state the fec workers share with them.
+/// Created at build time, so shreds can be pushed in through `UnshredIngest`.
+pub struct FecChannels {
+    pub router: ShredRouter,
+    pub receivers: Vec<StageReceiver<ShredBytesMeta>>,
+    pub shared: FecShared,
+}
+
+/// State shared by the fec workers, the router and the dispatcher
+#[derive(Clone)]
+pub struct FecShared {
+    pub processed_fec_sets: Arc<ProcessedFecSets>,
+    pub late_shreds: Arc<LateShreds>,
+    /// Forwards recovered data shreds, if the relay is configured to
+    pub recovered_relay: Option<Relay>,
+    /// Forwards shreds once their signature is verified, replacing the router's relay
+    /// with a leader schedule
+    pub verified_relay: Option<Relay>,
+    /// Quarantines senders of unparseable shreds
+    pub source_filter: Option<Arc<SourceFilter>>,
+    /// Highest slot with a completed FEC set, for shedding lagging slots
+    pub slot_tip: Arc<SlotTip>,
+    /// Drops shreds not signed by their slot leader
+    pub signature_verifier: Option<Arc<SignatureVerifier>>,
+    /// Slots whose leader equivocated
+    pub duplicate_slots: Arc<DuplicateSlots>,
+    /// FEC sets dropped before they could be recovered
+    pub incomplete_fec_sets: Arc<IncompleteFecSets>,
+}
+
+impl FecChannels {
+    pub fn new(
+        config: &UnshredConfig,
+        leader_schedule: Option<Arc<dyn LeaderSchedule>>,
+    ) -> Result<Self> {
+        // Shreds that lost the race to an already completed fec set, per feed
+        let late_shreds = Arc::new(LateShreds::default());
+
+        // One channel per fec worker
+        let num_fec_workers = match config.num_fec_workers {
+            Some(num) => num,
+            None => num_cpus::get().saturating_sub(2) as u8,
+        };
+        let num_fec_workers = std::cmp::max(num_fec_workers, 1);
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..num_fec_workers)
+            .map(|_| {
+                stage::channel::<ShredBytesMeta>(
+                    "fec_worker",
+                    &config.backpressure.fec_workers,
+                    10000,
+                    BackpressurePolicy::DropNewest,
+                )
+            })
+            .unzip();
+
+        let recorder = config.recording.as_ref().map(Recorder::start).transpose()?;
+        let relay = config.relay.as_ref().map(Relay::start).transpose()?;
+        let recovered_relay = config
+            .relay
+            .as_ref()
+            .filter(|relay_config| relay_config.forward_recovered)
+            .and(relay.clone());
+        let source_filter = config
+            .source_filter
+            .as_ref()
+            .map(SourceFilter::new)
+            .transpose()?
+            .map(Arc::new);
+        let shred_version = config
+            .shred_version
+            .as_ref()
+            .map(|shred_version| Arc::new(ShredVersionCheck::new(shred_version)));
+        let signature_verifier = leader_schedule
+            .map(|leader_schedule| Arc::new(SignatureVerifier::new(leader_schedule)));
+        // Completed FEC sets are signed by the leader with a schedule
+        let slot_tip = Arc::new(SlotTip::new(
+            config.max_slot_lag,
+            signature_verifier.is_some(),
+        ));
+        // Track processed fec sets of recent slots for deduplication
+        let processed_fec_sets = Arc::new(ProcessedFecSets::new(
+            config.dedup_window_slots,
+            Arc::clone(&slot_tip),
+        ));
+        // Spoofed shreds must not reach downstream either
+        let (relay, verified_relay) = match signature_verifier {
+            Some(_) => (None, relay),
+            None => (relay, None),
+        };
+
+        Ok(Self {
+            router: ShredRouter::new(
+                senders,
+                Arc::clone(&processed_fec_sets),
+                Arc::clone(&late_shreds),
+                Arc::clone(&slot_tip),
+                RouterHooks {
+                    source_filter: source_filter.clone(),
+                    recorder,
+                    shred_version,
+                    relay,
+                },
+            ),
+            receivers,
+            shared: FecShared {
+                processed_fec_sets,
+                late_shreds,
+                recovered_relay,
+                verified_relay,
+                source_filter,
+                slot_tip,
+                signature_verifier,
+                duplicate_slots: Arc::new(DuplicateSlots::default()),
+                incomplete_fec_sets: Arc::new(IncompleteFecSets::default()),
+            },
+        })
+    }
+}
+
 pub struct ShredProcessor {}
 
 impl ShredProcessor {
@@ -81,34 +471,63 @@ impl ShredProcessor {
         Self {}
     }
 
+    /// `inputs` - UDP inputs, each opened as one source per socket next to `sources`
     pub async fn run<H: TransactionHandler>(
         self,
         tx_handler: H,
         config: &UnshredConfig,
+        inputs: Vec<ShredInputConfig>,
+        mut sources: Vec<Box<dyn ShredSource>>,
+        fec_channels: FecChannels,
     ) -> Result<()> {
         let total_cores = num_cpus::get();
         // Channel for fec workers -> batch dispatcher worker
-        let (completed_fec_sender, completed_fec_receiver) =
-            tokio::sync::mpsc::channel::<CompletedFecSet>(1000);
+        let (completed_fec_sender, completed_fec_receiver) = stage::channel::<CompletedFecSet>(
+            "dispatch",
+            &config.backpressure.dispatch,
+            1000,
+            BackpressurePolicy::Block { timeout_ms: None },
+        );
 
-        // Track processed fec sets for  deduplication
-        let processed_fec_sets = Arc::new(DashSet::<(u64, u32)>::new());
+        let FecChannels {
+            router,
+            receivers: shred_receivers,
+            shared,
+        } = fec_channels;
+
+        // Network receivers, one source per socket
+        let num_receivers = std::cmp::max(config.num_receivers.unwrap_or(1), 1) as usize;
+        let recv_batch_size = config
+            .recv_batch_size
+            .unwrap_or(crate::receiver::DEFAULT_RECV_BATCH_SIZE);
+        for input in inputs {
+            let bind_addr: std::net::SocketAddr = input.bind_address.parse()?;
+            let receiver = ShredReceiver::new(
+                &input.name,
+                bind_addr,
+                num_receivers,
+                recv_batch_size,
+                config.timestamp_source,
+                input.multicast.as_ref(),
+            )?;
+            sources.extend(
+                receiver
+                    .into_sources()
+                    .into_iter()
+                    .map(|source| Box::new(source) as Box<dyn ShredSource>),
+            );
+        }
 
-        // Channels for receiver -> fec workers
-        let num_fec_workers = match config.num_fec_workers {
-            Some(num) => num,
-            None => total_cores.saturating_sub(2) as u8,
-        };
-        let num_fec_workers = std::cmp::max(num_fec_workers, 1);
-        let (shred_senders, shred_receivers): (Vec<_>, Vec<_>) = (0..num_fec_workers)
-            .map(|_| tokio::sync::mpsc::channel::<ShredBytesMeta>(10000))
-            .unzip();
+        // Spawn shred sources
+        info!("Starting {} shred sources", sources.len());
+        let receiver_handles: Vec<_> = sources
+            .into_iter()
+            .map(|source| tokio::spawn(run_source(source, router.clone())))
+            .collect();
+        drop(router);
 
-        // Spawn network receiver
-        let bind_addr: std::net::SocketAddr = config.bind_address.parse()?;
-        let receiver = crate::receiver::ShredReceiver::new(bind_addr)?;
-        let receiver_handle =
-            tokio::spawn(receiver.run(shred_senders, Arc::clone(&processed_fec_sets)));
+        // Channel for fec workers and batch dispatch worker -> slot event worker
+        let (event_sender, event_receiver) = tokio::sync::mpsc::channel::<SlotEvent>(1000);
 
         // Spawn fec workers
         info!(
@@ -119,18 +538,26 @@ impl ShredProcessor {
         let mut fec_handles = Vec::new();
         for (worker_id, fec_receiver) in shred_receivers.into_iter().enumerate() {
             let sender = completed_fec_sender.clone();
-            let processed_fec_sets_clone = Arc::clone(&processed_fec_sets);
+            let event_sender_clone = event_sender.clone();
+            let shared_clone = shared.clone();
 
             let handle = tokio::spawn(async move {
-                if let Err(e) =
-                    Self::run_fec_worker(worker_id, fec_receiver, sender, processed_fec_sets_clone)
-                        .await
+                if let Err(e) = Self::run_fec_worker(
+                    worker_id,
+                    fec_receiver,
+                    sender,
+                    event_sender_clone,
+                    shared_clone,
+                )
+                .await
                 {
                     error!("FEC worker {} failed: {}", worker_id, e);
                 }
             });
             fec_handles.push(handle);
         }
+        // Dispatch ends once every fec worker is done
+        drop(completed_fec_sender);
 
         // Channels for batch dispatch worker -> batch processing workers
         let num_batch_workers = match config.num_batch_workers {
@@ -139,46 +566,87 @@ impl ShredProcessor {
         };
         let num_batch_workers = std::cmp::max(num_batch_workers, 1);
         let (batch_senders, batch_receivers): (Vec<_>, Vec<_>) = (0..num_batch_workers)
-            .map(|_| tokio::sync::mpsc::channel::<BatchWork>(10000))
+            .map(|_| {
+                stage::channel::<BatchWork>(
+                    "batch_worker",
+                    &config.backpressure.batch_workers,
+                    10000,
+                    BackpressurePolicy::Block { timeout_ms: None },
+                )
+            })
             .unzip();
 
+        let tx_handler = Arc::new(tx_handler);
+
         // Spawn batch dispatch worker
         let processor = Arc::new(self);
         let dispatch_handle = {
             let senders = batch_senders.clone();
             let proc = Arc::clone(&processor);
+            let event_sender_clone = event_sender.clone();
+            let shared_clone = shared.clone();
 
             tokio::spawn(async move {
-                if let Err(e) = proc.dispatch_worker(completed_fec_receiver, senders).await {
+                if let Err(e) = proc
+                    .dispatch_worker(
+                        completed_fec_receiver,
+                        senders,
+                        event_sender_clone,
+                        shared_clone,
+                    )
+                    .await
+                {
                     error!("Accumulation worker failed: {:?}", e)
                 }
             })
         };
 
+        // Spawn slot event worker
+        let event_handle = {
+            let tx_handler_clone = Arc::clone(&tx_handler);
+
+            tokio::spawn(async move {
+                if let Err(e) = Self::event_worker(event_receiver, tx_handler_clone).await {
+                    error!("Slot event worker failed: {:?}", e);
+                }
+            })
+        };
+
         // Spawn batch workers
         info!(
             "Starting {} batch workers on {} cores",
             num_batch_workers, total_cores
         );
 
-        let tx_handler = Arc::new(tx_handler);
         let mut batch_handles = Vec::new();
         for (worker_id, batch_receiver) in batch_receivers.into_iter().enumerate() {
             let tx_handler_clone = Arc::clone(&tx_handler);
+            let event_sender_clone = event_sender.clone();
+            let slot_tip_clone = Arc::clone(&shared.slot_tip);
 
             let handle = tokio::spawn(async move {
-                if let Err(e) =
-                    Self::batch_worker(worker_id, batch_receiver, tx_handler_clone).await
+                if let Err(e) = Self::batch_worker(
+                    worker_id,
+                    batch_receiver,
+                    tx_handler_clone,
+                    event_sender_clone,
+                    slot_tip_clone,
+                )
+                .await
                 {
                     error!("Batch worker {} failed: {:?}", worker_id, e);
                 }
             });
             batch_handles.push(handle);
         }
+        // Slot events end once every worker is done
+        drop(event_sender);
 
         // Wait for all workers to complete
         dispatch_handle.await?;
-        let _ = tokio::time::timeout(Duration::from_secs(5), receiver_handle).await;
+        for handle in receiver_handles {
+            let _ = tokio::time::timeout(Duration::from_secs(5), handle).await;
+        }
         for handle in fec_handles {
             let _ = tokio::time::timeout(Duration::from_secs(5), handle).await;
         }
@@ -186,18 +654,24 @@ impl ShredProcessor {
         for handle in batch_handles {
             let _ = tokio::time::timeout(Duration::from_secs(5), handle).await;
         }
+        let _ = tokio::time::timeout(Duration::from_secs(5), event_handle).await;
 
         Ok(())
     }
 
     async fn run_fec_worker(
         worker_id: usize,
-        mut receiver: Receiver<ShredBytesMeta>,
-        sender: Sender<CompletedFecSet>,
-        processed_fec_sets: Arc<DashSet<(u64, u32)>>,
+        mut receiver: StageReceiver<ShredBytesMeta>,
+        sender: StageSender<CompletedFecSet>,
+        event_sender: Sender<SlotEvent>,
+        shared: FecShared,
     ) -> Result<()> {
         let reed_solomon_cache = Arc::new(ReedSolomonCache::default());
-        let mut fec_set_accumulators: HashMap<(u64, u32), FecSetAccumulator> = HashMap::new();
+        let mut fec_set_accumulators: HashMap<(u64, u32), FecSetBuckets> = HashMap::new();
+        let mut duplicate_detector = DuplicateDetector::new(
+            Arc::clone(&shared.duplicate_slots),
+            shared.signature_verifier.is_some(),
+        );
         let mut last_cleanup = Instant::now();
         #[cfg(feature = "metrics")]
         let mut last_channel_udpate = Instant::now();
@@ -208,9 +682,11 @@ impl ShredProcessor {
                     if let Err(e) = Self::process_fec_shred(
                         shred_bytes_meta,
                         &mut fec_set_accumulators,
+                        &mut duplicate_detector,
                         &sender,
+                        &event_sender,
                         &reed_solomon_cache,
-                        &processed_fec_sets,
+                        &shared,
                     )
                     .await
                     {
@@ -219,6 +695,15 @@ impl ShredProcessor {
                 }
                 None => {
                     warn!("FEC worker {} disconnected", worker_id);
+
+                    // Sources are exhausted, the remaining FEC sets will not complete
+                    for (fec_key, fec_set) in fec_set_accumulators.drain() {
+                        Self::record_incomplete_fec_set(
+                            fec_key,
+                            fec_set.buckets,
+                            &shared.incomplete_fec_sets,
+                        );
+                    }
                     break;
                 }
             }
@@ -237,8 +722,14 @@ impl ShredProcessor {
                 last_channel_udpate = std::time::Instant::now();
             }
 
-            if last_cleanup.elapsed() > Duration::from_secs(30) {
-                Self::cleanup_fec_sets(&mut fec_set_accumulators);
+            // Often enough for evicted FEC sets to reach the slot's incomplete report
+            if last_cleanup.elapsed() > Duration::from_secs(1) {
+                Self::cleanup_fec_sets(
+                    &mut fec_set_accumulators,
+                    &shared.slot_tip,
+                    &shared.incomplete_fec_sets,
+                );
+                duplicate_detector.cleanup(Duration::from_secs(30), &shared.slot_tip);
                 last_cleanup = Instant::now();
             }
         }
@@ -248,15 +739,22 @@ impl ShredProcessor {
 
     async fn process_fec_shred(
         shred_bytes_meta: ShredBytesMeta,
-        fec_set_accumulators: &mut HashMap<(u64, u32), FecSetAccumulator>,
-        sender: &Sender<CompletedFecSet>,
+        fec_set_accumulators: &mut HashMap<(u64, u32), FecSetBuckets>,
+        duplicate_detector: &mut DuplicateDetector,
+        sender: &StageSender<CompletedFecSet>,
+        event_sender: &Sender<SlotEvent>,
         reed_solomon_cache: &Arc<ReedSolomonCache>,
-        processed_fec_sets: &DashSet<(u64, u32)>,
+        shared: &FecShared,
     ) -> Result<()> {
         let shred = match Shred::new_from_serialized_shred(shred_bytes_meta.shred_bytes.to_vec()) {
             Ok(shred) => shred,
             Err(e) => {
                 error!("Failed to parse shred: {}", e);
+                if let (Some(source_filter), Some(peer_addr)) =
+                    (&shared.source_filter, shred_bytes_meta.origin.peer_addr)
+                {
+                    source_filter.report_parse_failure(peer_addr.ip());
+                }
                 return Ok(());
             }
         };
@@ -265,16 +763,96 @@ impl ShredProcessor {
         let fec_set_index = shred.fec_set_index();
         let fec_key = (slot, fec_set_index);
 
-        let accumulator =
-            fec_set_accumulators
-                .entry(fec_key)
-                .or_insert_with(|| FecSetAccumulator {
-                    slot: slot,
+        if shared.slot_tip.shed(slot, "fec_worker") {
+            return Ok(());
+        }
+
+        // Completed while this shred was queued. Shreds of the other version of an
+        // equivocated FEC set are signed differently and accumulate in their own bucket.
+        if shared
+            .processed_fec_sets
+            .contains_signed(&fec_key, &shred.payload()[..SIGNATURE_LEN])
+        {
+            shared
+                .late_shreds
+                .record(slot, &shred_bytes_meta.origin.feed);
+            return Ok(());
+        }
+
+        // Computed from the shred's merkle proof, a shred whose proof does not
+        // lead to the root of the other shreds lands in a bucket of its own
+        let merkle_root = match shred.merkle_root() {
+            Ok(merkle_root) => merkle_root,
+            Err(e) => {
+                trace!("Failed to compute merkle root: {}", e);
+                Self::count_fec_error("merkle_root");
+                return Ok(());
+            }
+        };
+
+        // Verified once per root, the other shreds of the root are proven by their merkle proof
+        let fec_set = fec_set_accumulators.get(&fec_key);
+        if fec_set.is_some_and(|fec_set| fec_set.rejected_roots.contains(&merkle_root)) {
+            Self::count_fec_error("merkle_root_rejected");
+            return Ok(());
+        }
+        if fec_set.is_none_or(|fec_set| fec_set.position(&merkle_root).is_none()) {
+            if let Some(signature_verifier) = &shared.signature_verifier {
+                if !signature_verifier.verify(&shred) {
+                    fec_set_accumulators
+                        .entry(fec_key)
+                        .or_insert_with(FecSetBuckets::new)
+                        .reject(merkle_root);
+                    return Ok(());
+                }
+            }
+            if let Some(duplicate) = duplicate_detector.check(fec_key, merkle_root, &shred) {
+                Self::report_duplicate(duplicate, event_sender).await;
+            }
+        }
+
+        let fec_set = fec_set_accumulators
+            .entry(fec_key)
+            .or_insert_with(FecSetBuckets::new);
+        let bucket = match fec_set.position(&merkle_root) {
+            Some(bucket) => bucket,
+            None => {
+                if !fec_set.buckets.is_empty() {
+                    Self::count_fec_error("merkle_root_mismatch");
+                }
+                if fec_set.buckets.len() >= MAX_BUCKETS_PER_FEC_SET {
+                    fec_set.evict_smallest();
+                    Self::count_fec_error("merkle_root_bucket_evicted");
+                }
+                fec_set.buckets.push(FecSetAccumulator {
+                    slot,
+                    merkle_root,
                     data_shreds: HashMap::new(),
                     code_shreds: HashMap::new(),
                     expected_data_shreds: None,
+                    recovered_shreds: 0,
+                    race: FecSetRace::default(),
                     created_at: Instant::now(),
                 });
+                fec_set.buckets.len() - 1
+            }
+        };
+        let accumulator = &mut fec_set.buckets[bucket];
+        // Signed by the leader, or proven to share a signed root
+        if let Some(relay) = &shared.verified_relay {
+            relay.forward(shred.payload());
+        }
+
+        let index = shred.index();
+        let redundant = match shred.shred_type() {
+            ShredType::Code => accumulator.code_shreds.contains_key(&index),
+            ShredType::Data => accumulator.data_shreds.contains_key(&index),
+        };
+        accumulator.race.record(
+            &shred_bytes_meta.origin.feed,
+            shred_bytes_meta.received_at_micros,
+            redundant,
+        );
 
         let shred_meta = ShredMeta {
             shred,
@@ -284,22 +862,34 @@ impl ShredProcessor {
         Self::store_fec_shred(accumulator, shred_meta)?;
         Self::check_fec_completion(
             fec_key,
+            merkle_root,
             fec_set_accumulators,
             sender,
             reed_solomon_cache,
-            processed_fec_sets,
+            shared,
         )
         .await?;
 
         Ok(())
     }
 
+    async fn report_duplicate(duplicate: DuplicateSlotEvent, event_sender: &Sender<SlotEvent>) {
+        if let Err(e) = event_sender.send(SlotEvent::Duplicate(duplicate)).await {
+            error!("Failed to send duplicate slot event: {}", e);
+        }
+    }
+
     fn store_fec_shred(accumulator: &mut FecSetAccumulator, shred_meta: ShredMeta) -> Result<()> {
         match shred_meta.shred.shred_type() {
             ShredType::Code => {
                 let payload = shred_meta.shred.payload();
-                if accumulator.expected_data_shreds.is_none() && payload.len() >= 85 {
-                    let expected = u16::from_le_bytes([payload[83], payload[84]]) as usize;
+                if accumulator.expected_data_shreds.is_none()
+                    && payload.len() >= OFFSET_NUM_DATA + 2
+                {
+                    let expected = u16::from_le_bytes([
+                        payload[OFFSET_NUM_DATA],
+                        payload[OFFSET_NUM_DATA + 1],
+                    ]) as usize;
                     accumulator.expected_data_shreds = Some(expected);
                 }
 
@@ -333,25 +923,29 @@ impl ShredProcessor {
         Ok(())
     }
 
-    /// Checks if FEC sets are fully reconstructed and sends them to dispatcher if they are
+    /// Checks if FEC sets are fully reconstructed and sends them to dispatcher if they are.
+    /// Buckets of other merkle roots are kept, each version of a FEC set completes on its own.
     async fn check_fec_completion(
         fec_key: (u64, u32),
-        fec_set_accumulators: &mut HashMap<(u64, u32), FecSetAccumulator>,
-        sender: &Sender<CompletedFecSet>,
+        merkle_root: Hash,
+        fec_set_accumulators: &mut HashMap<(u64, u32), FecSetBuckets>,
+        sender: &StageSender<CompletedFecSet>,
         reed_solomon_cache: &Arc<ReedSolomonCache>,
-        processed_fec_sets: &DashSet<(u64, u32)>,
+        shared: &FecShared,
     ) -> Result<()> {
-        let acc = if let Some(accumulator) = fec_set_accumulators.get_mut(&fec_key) {
-            accumulator
-        } else {
+        let Some(fec_set) = fec_set_accumulators.get_mut(&fec_key) else {
+            return Ok(());
+        };
+        let Some(bucket) = fec_set.position(&merkle_root) else {
             return Ok(());
         };
+        let acc = &mut fec_set.buckets[bucket];
         let status = Self::can_reconstruct_fec_set(acc);
 
         match status {
             ReconstructionStatus::ReadyNatural => {
-                let acc = fec_set_accumulators.remove(&fec_key).unwrap();
-                Self::send_completed_fec_set(acc, sender, fec_key, processed_fec_sets).await?;
+                let acc = Self::remove_bucket(fec_set_accumulators, fec_key, bucket);
+                Self::send_completed_fec_set(acc, sender, fec_key, shared).await?;
 
                 #[cfg(feature = "metrics")]
                 if let Some(metrics) = Metrics::try_get() {
@@ -362,13 +956,16 @@ impl ShredProcessor {
                 }
             }
             ReconstructionStatus::ReadyRecovery => {
-                if let Err(e) = Self::recover_fec(acc, reed_solomon_cache).await {
+                if let Err(e) =
+                    Self::recover_fec(acc, reed_solomon_cache, shared.recovered_relay.as_ref())
+                        .await
+                {
                     error!("FEC Recovery failed unexpectedly: {:?}", e);
                     return Ok(());
                 }
 
-                let acc = fec_set_accumulators.remove(&fec_key).unwrap();
-                Self::send_completed_fec_set(acc, sender, fec_key, processed_fec_sets).await?;
+                let acc = Self::remove_bucket(fec_set_accumulators, fec_key, bucket);
+                Self::send_completed_fec_set(acc, sender, fec_key, shared).await?;
 
                 #[cfg(feature = "metrics")]
                 if let Some(metrics) = Metrics::try_get() {
@@ -384,6 +981,20 @@ impl ShredProcessor {
         Ok(())
     }
 
+    fn remove_bucket(
+        fec_set_accumulators: &mut HashMap<(u64, u32), FecSetBuckets>,
+        fec_key: (u64, u32),
+        bucket: usize,
+    ) -> FecSetAccumulator {
+        let fec_set = fec_set_accumulators.get_mut(&fec_key).unwrap();
+        let acc = fec_set.buckets.swap_remove(bucket);
+        // Rejected roots are kept until the FEC set ages out
+        if fec_set.buckets.is_empty() && fec_set.rejected_roots.is_empty() {
+            fec_set_accumulators.remove(&fec_key);
+        }
+        acc
+    }
+
     fn can_reconstruct_fec_set(acc: &FecSetAccumulator) -> ReconstructionStatus {
         let data_count = acc.data_shreds.len();
         let code_count = acc.code_shreds.len();
@@ -412,6 +1023,7 @@ impl ShredProcessor {
     async fn recover_fec(
         acc: &mut FecSetAccumulator,
         reed_solomon_cache: &Arc<ReedSolomonCache>,
+        recovered_relay: Option<&Relay>,
     ) -> Result<()> {
         let mut shreds_for_recovery = Vec::new();
 
@@ -431,6 +1043,10 @@ impl ShredProcessor {
                             if recovered_shred.is_data() {
                                 let index = recovered_shred.index();
                                 if !acc.data_shreds.contains_key(&index) {
+                                    if let Some(relay) = recovered_relay {
+                                        relay.forward(recovered_shred.payload());
+                                    }
+                                    acc.recovered_shreds += 1;
                                     acc.data_shreds.insert(
                                         index,
                                         ShredMeta {
@@ -457,17 +1073,38 @@ impl ShredProcessor {
 
     async fn send_completed_fec_set(
         acc: FecSetAccumulator,
-        sender: &Sender<CompletedFecSet>,
+        sender: &StageSender<CompletedFecSet>,
         fec_key: (u64, u32),
-        processed_fec_sets: &DashSet<(u64, u32)>,
+        shared: &FecShared,
     ) -> Result<()> {
+        // Shared by every shred of the root
+        let first_shred = acc
+            .data_shreds
+            .values()
+            .chain(acc.code_shreds.values())
+            .next()
+            .map(|shred_meta| &shred_meta.shred);
+        let chained_merkle_root = first_shred.and_then(|shred| shred.chained_merkle_root().ok());
+        let mut signature = [0; SIGNATURE_LEN];
+        if let Some(shred) = first_shred {
+            signature.copy_from_slice(&shred.payload()[..SIGNATURE_LEN]);
+        }
         let completed_fec_set = CompletedFecSet {
             slot: acc.slot,
+            fec_set_index: fec_key.1,
+            merkle_root: acc.merkle_root,
+            chained_merkle_root,
             data_shreds: acc.data_shreds,
+            recovered_shreds: acc.recovered_shreds,
+            race: acc.race,
         };
 
         sender.send(completed_fec_set).await?;
-        processed_fec_sets.insert(fec_key);
+        // A completed FEC set is proven by its merkle root, and by the leader's signature
+        // with a leader schedule, so a single forged shred cannot move the tip.
+        // Without one, a forged FEC set far ahead cannot either.
+        shared.slot_tip.observe(fec_key.0);
+        shared.processed_fec_sets.insert(fec_key, signature);
 
         Ok(())
     }
@@ -475,70 +1112,107 @@ impl ShredProcessor {
     /// Pulls completed FEC sets, tries to reconstruct batches and dispatch them
     async fn dispatch_worker(
         self: Arc<Self>,
-        mut completed_fec_receiver: Receiver<CompletedFecSet>,
-        batch_sender: Vec<Sender<BatchWork>>,
+        mut completed_fec_receiver: StageReceiver<CompletedFecSet>,
+        batch_sender: Vec<StageSender<BatchWork>>,
+        event_sender: Sender<SlotEvent>,
+        shared: FecShared,
     ) -> Result<()> {
         let mut slot_accumulators: HashMap<u64, SlotAccumulator> = HashMap::new();
-        let mut processed_slots = HashSet::new();
+        let mut completed_slots = CompletedSlots::default();
+        let mut incomplete_slots = IncompleteSlots::new(Arc::clone(&shared.incomplete_fec_sets));
         let mut next_worker = 0usize;
         let mut last_maintenance = Instant::now();
 
         loop {
-            match completed_fec_receiver.recv().await {
-                Some(completed_fec_set) => {
-                    if let Err(e) = self
+            // Also wakes up without FEC sets arriving, so evicted slots of idle feeds are reported
+            let until_maintenance = MAINTENANCE_INTERVAL.saturating_sub(last_maintenance.elapsed());
+            match tokio::time::timeout(until_maintenance, completed_fec_receiver.recv()).await {
+                Ok(Some(completed_fec_set)) => {
+                    match self
                         .accumulate_completed_fec_set(
                             completed_fec_set,
                             &mut slot_accumulators,
-                            &processed_slots,
+                            &mut completed_slots,
                             &batch_sender,
                             &mut next_worker,
+                            &shared,
                         )
                         .await
                     {
-                        error!("Failed to process completed FEC set: {}", e);
+                        Ok(Some(complete)) => {
+                            Self::report_complete_slot(complete, &event_sender).await
+                        }
+                        Ok(None) => {}
+                        Err(e) => error!("Failed to process completed FEC set: {}", e),
                     }
+                }
 
-                    if last_maintenance.elapsed() > std::time::Duration::from_secs(1) {
-                        // Clean up
-                        if let Err(e) =
-                            Self::cleanup_memory(&mut slot_accumulators, &mut processed_slots)
-                        {
-                            error!("Could not clean up memory: {:?}", e)
-                        }
+                Ok(None) => {
+                    warn!("FEC accumulation worker: Channel closed");
 
-                        // Metrics
-                        #[cfg(feature = "metrics")]
-                        if let Err(e) = Self::update_resource_metrics(&mut slot_accumulators) {
-                            error!("Could not update resource metrics: {:?}", e)
-                        }
+                    // Sources are exhausted, report the slots still in flight
+                    let remaining = slot_accumulators.drain().collect();
+                    Self::report_evicted_slots(
+                        remaining,
+                        &slot_accumulators,
+                        &mut incomplete_slots,
+                        &event_sender,
+                        &shared,
+                        true,
+                    )
+                    .await;
+                    break;
+                }
+
+                Err(_) => {}
+            }
 
-                        last_maintenance = Instant::now();
+            if last_maintenance.elapsed() >= MAINTENANCE_INTERVAL {
+                // Clean up
+                match Self::cleanup_memory(&mut slot_accumulators) {
+                    Ok(evicted) => {
+                        Self::report_evicted_slots(
+                            evicted,
+                            &slot_accumulators,
+                            &mut incomplete_slots,
+                            &event_sender,
+                            &shared,
+                            false,
+                        )
+                        .await
                     }
+                    Err(e) => error!("Could not clean up memory: {:?}", e),
                 }
 
-                None => {
-                    warn!("FEC accumulation worker: Channel closed");
-                    break;
+                // Metrics
+                #[cfg(feature = "metrics")]
+                if let Err(e) = Self::update_resource_metrics(&mut slot_accumulators) {
+                    error!("Could not update resource metrics: {:?}", e)
                 }
+
+                last_maintenance = Instant::now();
             }
         }
 
         Ok(())
     }
 
+    /// Adds a completed FEC set to its slot and dispatches the batches it completes
+    /// # Returns
+    /// * `Some(_)` - the slot completed and every batch of it is already decoded
     async fn accumulate_completed_fec_set(
         &self,
         completed_fec_set: CompletedFecSet,
         slot_accumulators: &mut HashMap<u64, SlotAccumulator>,
-        processed_slots: &HashSet<u64>,
-        batch_senders: &[Sender<BatchWork>],
+        completed_slots: &mut CompletedSlots,
+        batch_senders: &[StageSender<BatchWork>],
         next_worker: &mut usize,
-    ) -> Result<()> {
+        shared: &FecShared,
+    ) -> Result<Option<SlotCompleteEvent>> {
         let slot = completed_fec_set.slot;
 
-        if processed_slots.contains(&slot) {
-            return Ok(());
+        if completed_slots.contains(slot) || shared.slot_tip.shed(slot, "dispatch") {
+            return Ok(None);
         }
 
         let accumulator = slot_accumulators
@@ -546,25 +1220,170 @@ impl ShredProcessor {
             .or_insert_with(|| SlotAccumulator {
                 data_shreds: HashMap::new(),
                 last_processed_batch_idx: None,
+                last_shred_idx: None,
+                recovered_shreds: 0,
+                progress: Arc::new(SlotProgress::new()),
+                merkle_chain: MerkleChain::default(),
+                equivocated: false,
+                race: SlotRace::default(),
                 created_at: Instant::now(),
             });
 
+        accumulator.race.add_fec_set(&completed_fec_set.race);
+        let verified = shared.signature_verifier.is_some();
+        if Self::conflicts(accumulator, &completed_fec_set, verified) {
+            // The other version of the block, kept out of the slot's batches
+            return Ok(None);
+        }
+        Self::verify_merkle_chain(accumulator, &completed_fec_set);
+
         // Add all data shreds from completed FEC set
         for (index, shred_meta) in completed_fec_set.data_shreds {
+            let last_in_slot = shred_meta
+                .shred
+                .payload()
+                .get(OFFSET_FLAGS)
+                .is_some_and(|flags| flags & LAST_SHRED_IN_SLOT == LAST_SHRED_IN_SLOT);
+            if last_in_slot {
+                accumulator.last_shred_idx = Some(index);
+            }
             accumulator.data_shreds.insert(index, shred_meta);
         }
+        accumulator.recovered_shreds += completed_fec_set.recovered_shreds;
+
+        // Batches of an equivocated slot could mix both versions of the block
+        if shared.duplicate_slots.contains(slot) {
+            accumulator.equivocated = true;
+        }
+        if accumulator.equivocated {
+            return Ok(None);
+        }
 
         self.try_dispatch_complete_batch(accumulator, slot, batch_senders, next_worker)
             .await?;
 
-        Ok(())
+        // Every batch up to the last shred in slot is dispatched
+        if accumulator.last_shred_idx.is_none()
+            || accumulator.last_processed_batch_idx != accumulator.last_shred_idx
+        {
+            return Ok(None);
+        }
+        completed_slots.insert(slot);
+
+        let received_at_micros = accumulator
+            .data_shreds
+            .values()
+            .filter_map(|shred_meta| shred_meta.received_at_micros);
+        let first_shred_received_at_micros = received_at_micros.clone().min();
+        let last_shred_received_at_micros = received_at_micros.max();
+        let complete = SlotCompleteEvent {
+            slot,
+            data_shreds: accumulator.data_shreds.len() as u32,
+            recovered_shreds: accumulator.recovered_shreds,
+            entries: 0,
+            transactions: 0,
+            first_shred_received_at_micros,
+            last_shred_received_at_micros,
+            // Every FEC set up to the last shred in slot is completed, their links are checked
+            merkle_chain: accumulator.merkle_chain.status(),
+        };
+        let _ = accumulator.progress.complete.set(complete);
+        Ok(accumulator.progress.release())
+    }
+
+    async fn report_complete_slot(complete: SlotCompleteEvent, event_sender: &Sender<SlotEvent>) {
+        if let Err(e) = event_sender.send(SlotEvent::Complete(complete)).await {
+            error!("Failed to send slot complete event: {}", e);
+        }
+    }
+
+    /// Whether `completed_fec_set` belongs to another version of the slot than the FEC sets
+    /// accumulated so far: another root for the same FEC set, or data shred indices overlapping
+    /// another FEC set. Flags the slot as equivocated if so and both versions are `verified`,
+    /// otherwise the version completed first is kept.
+    fn conflicts(
+        accumulator: &mut SlotAccumulator,
+        completed_fec_set: &CompletedFecSet,
+        verified: bool,
+    ) -> bool {
+        let fec_set_index = completed_fec_set.fec_set_index;
+        let conflicting = accumulator
+            .merkle_chain
+            .merkle_root(fec_set_index)
+            .is_some_and(|merkle_root| merkle_root != completed_fec_set.merkle_root)
+            || completed_fec_set.data_shreds.keys().any(|index| {
+                accumulator
+                    .data_shreds
+                    .get(index)
+                    .is_some_and(|shred_meta| shred_meta.shred.fec_set_index() != fec_set_index)
+            });
+        if !conflicting {
+            return false;
+        }
+
+        if !verified {
+            trace!(
+                "Slot {}: dropped unverified conflicting FEC set {}",
+                completed_fec_set.slot,
+                fec_set_index
+            );
+        } else if !accumulator.equivocated {
+            accumulator.equivocated = true;
+            warn!(
+                "Slot {} equivocated: conflicting FEC set {}",
+                completed_fec_set.slot, fec_set_index
+            );
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .errors
+                .with_label_values(&["dispatch", "conflicting_fec_set"])
+                .inc();
+        }
+
+        true
+    }
+
+    /// Checks that `completed_fec_set` chains to the merkle root of the previous FEC set
+    /// and the next FEC set to its root, flagging the slot if either link breaks
+    fn verify_merkle_chain(accumulator: &mut SlotAccumulator, completed_fec_set: &CompletedFecSet) {
+        let fec_set_index = completed_fec_set.fec_set_index;
+        let link = ChainLink {
+            merkle_root: completed_fec_set.merkle_root,
+            chained_merkle_root: completed_fec_set.chained_merkle_root,
+            next_fec_set_index: completed_fec_set
+                .data_shreds
+                .keys()
+                .max()
+                .map_or(fec_set_index, |index| index + 1),
+        };
+
+        let broken = accumulator.merkle_chain.broken;
+        accumulator.merkle_chain.add(fec_set_index, link);
+        if broken || !accumulator.merkle_chain.broken {
+            return;
+        }
+        warn!(
+            "Merkle root chain broken in slot {} at FEC set {}",
+            completed_fec_set.slot, fec_set_index
+        );
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .errors
+                .with_label_values(&["dispatch", "merkle_chain_broken"])
+                .inc();
+        }
     }
 
     async fn try_dispatch_complete_batch(
         &self,
         accumulator: &mut SlotAccumulator,
         slot: u64,
-        batch_senders: &[Sender<BatchWork>],
+        batch_senders: &[StageSender<BatchWork>],
         next_worker: &mut usize,
     ) -> Result<()> {
         let last_processed = accumulator.last_processed_batch_idx.unwrap_or(0);
@@ -580,7 +1399,7 @@ impl ShredProcessor {
 
                 let payload = shred_meta.shred.payload();
                 if let Some(data_flags) = payload.get(OFFSET_FLAGS) {
-                    if (data_flags & 0x40) != 0 {
+                    if (data_flags & DATA_COMPLETE_SHRED) != 0 {
                         Some(*idx)
                     } else {
                         None
@@ -620,9 +1439,12 @@ impl ShredProcessor {
                 batch_start_idx,
                 batch_end_idx,
                 shreds: batch_shreds,
+                progress: Arc::clone(&accumulator.progress),
+                decoded: false,
             };
             let sender = &batch_senders[*next_worker % batch_senders.len()];
             *next_worker += 1;
+            accumulator.progress.pending.fetch_add(1, Ordering::Relaxed);
             if let Err(e) = sender.send(batch_work).await {
                 return Err(anyhow::anyhow!("Failed to send batch work: {}", e));
             }
@@ -635,14 +1457,31 @@ impl ShredProcessor {
 
     async fn batch_worker<H: TransactionHandler>(
         worker_id: usize,
-        mut batch_receiver: Receiver<BatchWork>,
+        mut batch_receiver: StageReceiver<BatchWork>,
         tx_handler: Arc<H>,
+        event_sender: Sender<SlotEvent>,
+        slot_tip: Arc<SlotTip>,
     ) -> Result<()> {
         #[cfg(feature = "metrics")]
         let mut last_channel_udpate = std::time::Instant::now();
-        while let Some(batch_work) = batch_receiver.recv().await {
-            if let Err(e) = Self::process_batch_work(batch_work, &tx_handler).await {
-                error!("Batch worker {} failed to process batch: {}", worker_id, e);
+        while let Some(mut batch_work) = batch_receiver.recv().await {
+            // Batches dropped undecoded release their slot's pending count as failed
+            if slot_tip.shed(batch_work.slot, "batch_worker") {
+                continue;
+            }
+            match Self::process_batch_work(&batch_work, &tx_handler).await {
+                Ok((entries, transactions)) => {
+                    let progress = &batch_work.progress;
+                    progress.entries.fetch_add(entries, Ordering::Relaxed);
+                    progress
+                        .transactions
+                        .fetch_add(transactions, Ordering::Relaxed);
+                    batch_work.decoded = true;
+                    if let Some(complete) = progress.release() {
+                        Self::report_complete_slot(complete, &event_sender).await;
+                    }
+                }
+                Err(e) => error!("Batch worker {} failed to process batch: {}", worker_id, e),
             }
 
             // Update metrics periodically
@@ -665,10 +1504,12 @@ impl ShredProcessor {
         Ok(())
     }
 
+    /// # Returns
+    /// * `Ok((entries, transactions))` - decoded counts
     async fn process_batch_work<H: TransactionHandler>(
-        batch_work: BatchWork,
+        batch_work: &BatchWork,
         tx_handler: &Arc<H>,
-    ) -> Result<()> {
+    ) -> Result<(u32, u32)> {
         let combined_data_meta = Self::get_batch_data(
             &batch_work.shreds,
             batch_work.batch_start_idx,
@@ -676,12 +1517,16 @@ impl ShredProcessor {
         )?;
 
         let entries = Self::parse_entries_from_batch_data(combined_data_meta)?;
+        let transactions: usize = entries
+            .iter()
+            .map(|entry_meta| entry_meta.entry.transactions.len())
+            .sum();
 
-        for entry_meta in entries {
-            Self::process_entry_transactions(batch_work.slot, &entry_meta, tx_handler).await?;
+        for entry_meta in &entries {
+            Self::process_entry_transactions(batch_work.slot, entry_meta, tx_handler).await?;
         }
 
-        Ok(())
+        Ok((entries.len() as u32, transactions as u32))
     }
 
     fn get_batch_data(
@@ -837,16 +1682,69 @@ impl ShredProcessor {
         Ok(())
     }
 
-    fn cleanup_fec_sets(fec_sets: &mut HashMap<(u64, u32), FecSetAccumulator>) {
+    fn cleanup_fec_sets(
+        fec_sets: &mut HashMap<(u64, u32), FecSetBuckets>,
+        slot_tip: &SlotTip,
+        incomplete_fec_sets: &IncompleteFecSets,
+    ) {
         let now = Instant::now();
         let max_age = Duration::from_secs(30);
-        fec_sets.retain(|_, acc| now.duration_since(acc.created_at) <= max_age);
+        fec_sets.retain(|fec_key, fec_set| {
+            let (kept, evicted): (Vec<_>, Vec<_>) = std::mem::take(&mut fec_set.buckets)
+                .into_iter()
+                .partition(|acc| {
+                    now.duration_since(acc.created_at) <= max_age && !slot_tip.lags(acc.slot)
+                });
+            fec_set.buckets = kept;
+            if !fec_set.buckets.is_empty() {
+                return true;
+            }
+            Self::record_incomplete_fec_set(*fec_key, evicted, incomplete_fec_sets);
+            now.duration_since(fec_set.created_at) <= max_age && !slot_tip.lags(fec_key.0)
+        });
+    }
+
+    /// Records the evicted FEC set, by its bucket with the most shreds
+    fn record_incomplete_fec_set(
+        (slot, fec_set_index): (u64, u32),
+        buckets: Vec<FecSetAccumulator>,
+        incomplete_fec_sets: &IncompleteFecSets,
+    ) {
+        let Some(acc) = buckets
+            .into_iter()
+            .max_by_key(|acc| acc.data_shreds.len() + acc.code_shreds.len())
+        else {
+            return;
+        };
+
+        let mut data_shreds: Vec<u32> = acc.data_shreds.keys().copied().collect();
+        data_shreds.sort_unstable();
+        incomplete_fec_sets.record(
+            slot,
+            IncompleteFecSet {
+                fec_set_index,
+                data_shreds,
+                code_shreds: acc.code_shreds.len() as u32,
+                expected_data_shreds: acc.expected_data_shreds.map(|expected| expected as u32),
+            },
+        );
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn count_fec_error(error: &str) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .errors
+                .with_label_values(&["fec_worker", error])
+                .inc();
+        }
     }
 
+    /// Evicts slots older than 30s and returns them
     pub fn cleanup_memory(
         slot_accumulators: &mut HashMap<u64, SlotAccumulator>,
-        processed_slots: &mut HashSet<u64>,
-    ) -> Result<()> {
+    ) -> Result<Vec<(u64, SlotAccumulator)>> {
         let now = Instant::now();
         // Remove old slots from memory
         // We aren't expecting to get any more shreds for them
@@ -862,9 +1760,81 @@ impl ShredProcessor {
             })
             .collect();
 
+        let mut evicted = Vec::with_capacity(slots_to_remove.len());
         for slot in slots_to_remove {
-            slot_accumulators.remove(&slot);
-            processed_slots.remove(&slot);
+            if let Some(acc) = slot_accumulators.remove(&slot) {
+                evicted.push((slot, acc));
+            }
+        }
+
+        Ok(evicted)
+    }
+
+    /// Emits the per-slot summaries of evicted slots
+    /// and, once their unfinished FEC sets are in, the incomplete ones.
+    /// `flush` reports the incomplete slots without waiting.
+    async fn report_evicted_slots(
+        evicted: Vec<(u64, SlotAccumulator)>,
+        slot_accumulators: &HashMap<u64, SlotAccumulator>,
+        incomplete_slots: &mut IncompleteSlots,
+        event_sender: &Sender<SlotEvent>,
+        shared: &FecShared,
+        flush: bool,
+    ) {
+        for (slot, mut acc) in evicted {
+            if let Some(late) = shared.late_shreds.take(slot) {
+                acc.race.add_late(late);
+            }
+            incomplete_slots.evict(
+                slot,
+                EvictedSlot {
+                    data_shreds: acc.data_shreds.keys().copied().collect(),
+                    last_shred_idx: acc.last_shred_idx,
+                    progress: acc.progress,
+                },
+            );
+            if let Err(e) = event_sender
+                .send(SlotEvent::Race(acc.race.into_event(slot)))
+                .await
+            {
+                error!("Failed to send slot race event: {}", e);
+            }
+        }
+
+        // Late shreds for already reported slots are never picked up
+        if let Some(oldest_active) = slot_accumulators.keys().min() {
+            shared.late_shreds.prune_below(*oldest_active);
+        }
+
+        if flush {
+            incomplete_slots.settle(Duration::from_secs(5)).await;
+        }
+        let incomplete = incomplete_slots.take_due(
+            |slot| slot_accumulators.contains_key(&slot),
+            shared.slot_tip.highest(),
+            flush,
+        );
+        for event in incomplete {
+            if let Err(e) = event_sender.send(SlotEvent::Incomplete(event)).await {
+                error!("Failed to send slot incomplete event: {}", e);
+            }
+        }
+    }
+
+    async fn event_worker<H: TransactionHandler>(
+        mut event_receiver: Receiver<SlotEvent>,
+        handler: Arc<H>,
+    ) -> Result<()> {
+        while let Some(event) = event_receiver.recv().await {
+            let result = match &event {
+                SlotEvent::Race(race) => handler.handle_slot_race(race),
+                SlotEvent::Duplicate(duplicate) => handler.handle_duplicate_slot(duplicate),
+                SlotEvent::Complete(complete) => handler.handle_slot_complete(complete),
+                SlotEvent::Incomplete(incomplete) => handler.handle_slot_incomplete(incomplete),
+            };
+            if let Err(e) = result {
+                error!("Slot event handler error: {:?}", e);
+            }
         }
 
         Ok(())
@@ -885,3 +1855,436 @@ impl ShredProcessor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingest::UnshredIngest, DuplicateSlotEvent, SlotIncompleteEvent};
+    use solana_keypair::Keypair;
+    use solana_ledger::shred::{ProcessShredsStats, Shredder};
+    use solana_pubkey::Pubkey;
+    use solana_signer::Signer;
+    use solana_transaction::versioned::VersionedTransaction;
+    use std::sync::{atomic::AtomicUsize, Mutex};
+
+    const SLOT: u64 = 1000;
+
+    /// Leader of every slot, counting signature verifications
+    struct Leader {
+        pubkey: Pubkey,
+        lookups: AtomicUsize,
+    }
+
+    impl Leader {
+        fn new(keypair: &Keypair) -> Arc<Self> {
+            Arc::new(Self {
+                pubkey: keypair.pubkey(),
+                lookups: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    impl LeaderSchedule for Leader {
+        fn slot_leader(&self, _slot: u64) -> Option<Pubkey> {
+            self.lookups.fetch_add(1, Ordering::Relaxed);
+            Some(self.pubkey)
+        }
+    }
+
+    #[derive(Default)]
+    struct Events {
+        transactions: usize,
+        duplicates: Vec<DuplicateSlotEvent>,
+        complete: Vec<SlotCompleteEvent>,
+        incomplete: Vec<SlotIncompleteEvent>,
+    }
+
+    struct Collector(Arc<Mutex<Events>>);
+
+    impl TransactionHandler for Collector {
+        fn handle_transaction(&self, _event: &TransactionEvent) -> Result<()> {
+            self.0.lock().unwrap().transactions += 1;
+            Ok(())
+        }
+
+        fn handle_duplicate_slot(&self, event: &DuplicateSlotEvent) -> Result<()> {
+            self.0.lock().unwrap().duplicates.push(event.clone());
+            Ok(())
+        }
+
+        fn handle_slot_complete(&self, event: &SlotCompleteEvent) -> Result<()> {
+            self.0.lock().unwrap().complete.push(event.clone());
+            Ok(())
+        }
+
+        fn handle_slot_incomplete(&self, event: &SlotIncompleteEvent) -> Result<()> {
+            self.0.lock().unwrap().incomplete.push(event.clone());
+            Ok(())
+        }
+    }
+
+    /// One FEC set of `slot` starting at `fec_set_index`, decoding to `transactions` transactions
+    fn fec_set(
+        keypair: &Keypair,
+        slot: u64,
+        fec_set_index: u32,
+        transactions: usize,
+        last_in_slot: bool,
+        chained_merkle_root: Hash,
+    ) -> Vec<Shred> {
+        let entries = vec![Entry {
+            num_hashes: 1,
+            hash: Hash::default(),
+            transactions: vec![VersionedTransaction::default(); transactions],
+        }];
+        Shredder::new(slot, slot - 1, 0, 0)
+            .unwrap()
+            .make_merkle_shreds_from_entries(
+                keypair,
+                &entries,
+                last_in_slot,
+                chained_merkle_root,
+                fec_set_index,
+                fec_set_index,
+                &ReedSolomonCache::default(),
+                &mut ProcessShredsStats::default(),
+            )
+            .collect()
+    }
+
+    fn merkle_root(shreds: &[Shred]) -> Hash {
+        shreds[0].merkle_root().unwrap()
+    }
+
+    /// Runs the pipeline over `shreds` in order, until every shred is processed
+    async fn run(shreds: Vec<Shred>, leader: Option<Arc<Leader>>) -> Events {
+        let config = UnshredConfig {
+            num_fec_workers: Some(1),
+            num_batch_workers: Some(1),
+            ..Default::default()
+        };
+        let leader_schedule = leader.map(|leader| leader as Arc<dyn LeaderSchedule>);
+        let fec_channels = FecChannels::new(&config, leader_schedule).unwrap();
+        let ingest = UnshredIngest::new(fec_channels.router.clone());
+        let events = Arc::new(Mutex::new(Events::default()));
+        let handler = Collector(Arc::clone(&events));
+        let processor = tokio::spawn(async move {
+            ShredProcessor::new()
+                .run(handler, &config, Vec::new(), Vec::new(), fec_channels)
+                .await
+        });
+
+        for shred in shreds {
+            ingest.push(shred.payload(), None).unwrap();
+        }
+        drop(ingest);
+        processor.await.unwrap().unwrap();
+
+        Arc::try_unwrap(events).ok().unwrap().into_inner().unwrap()
+    }
+
+    /// A two FEC set slot, with another version of its first FEC set after the genuine one
+    fn equivocating_slot(leader: &Keypair, other: &Keypair) -> Vec<Shred> {
+        let first = fec_set(leader, SLOT, 0, 2, false, Hash::default());
+        let conflicting = fec_set(other, SLOT, 0, 3, false, Hash::default());
+        let last = fec_set(leader, SLOT, 32, 2, true, merkle_root(&first));
+
+        [first, conflicting, last].concat()
+    }
+
+    #[tokio::test]
+    async fn unverified_conflict_keeps_first_version() {
+        let leader = Keypair::new();
+        let events = run(equivocating_slot(&leader, &Keypair::new()), None).await;
+
+        assert_eq!(events.duplicates.len(), 1);
+        assert!(!events.duplicates[0].verified);
+        assert_eq!(events.complete.len(), 1);
+        assert_eq!(events.complete[0].transactions, 4);
+        assert_eq!(events.complete[0].merkle_chain, MerkleChainStatus::Verified);
+        assert_eq!(events.transactions, 4);
+    }
+
+    #[tokio::test]
+    async fn verified_conflict_stops_slot() {
+        let leader = Keypair::new();
+        let events = run(
+            equivocating_slot(&leader, &leader),
+            Some(Leader::new(&leader)),
+        )
+        .await;
+
+        assert_eq!(events.duplicates.len(), 1);
+        assert!(events.duplicates[0].verified);
+        assert!(events.complete.is_empty());
+    }
+
+    #[tokio::test]
+    async fn spoofed_conflict_is_dropped_with_leader_schedule() {
+        let leader = Keypair::new();
+        let events = run(
+            equivocating_slot(&leader, &Keypair::new()),
+            Some(Leader::new(&leader)),
+        )
+        .await;
+
+        assert!(events.duplicates.is_empty());
+        assert_eq!(events.complete.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn spoofed_roots_do_not_crowd_out_genuine_fec_set() {
+        let leader = Keypair::new();
+        // More spoofed roots than buckets, a few shreds each
+        let mut shreds: Vec<Shred> = (0..2 * MAX_BUCKETS_PER_FEC_SET)
+            .flat_map(|transactions| {
+                fec_set(
+                    &Keypair::new(),
+                    SLOT,
+                    0,
+                    transactions,
+                    true,
+                    Hash::default(),
+                )
+                .into_iter()
+                .take(3)
+            })
+            .collect();
+        shreds.extend(fec_set(&leader, SLOT, 0, 2, true, Hash::default()));
+
+        let events = run(shreds, None).await;
+
+        assert_eq!(events.complete.len(), 1);
+        assert_eq!(events.transactions, 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_root_is_verified_once() {
+        let leader = Keypair::new();
+        let schedule = Leader::new(&leader);
+        let spoofed = fec_set(&Keypair::new(), SLOT, 0, 3, true, Hash::default());
+        let genuine = fec_set(&leader, SLOT, 0, 2, true, Hash::default());
+        assert!(spoofed.len() > 1);
+
+        let events = run([spoofed, genuine].concat(), Some(Arc::clone(&schedule))).await;
+
+        assert_eq!(events.complete.len(), 1);
+        assert_eq!(schedule.lookups.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn broken_chain_is_reported_on_completion() {
+        let leader = Keypair::new();
+        let first = fec_set(&leader, SLOT, 0, 2, false, Hash::default());
+        let last = fec_set(&leader, SLOT, 32, 2, true, Hash::new_from_array([7; 32]));
+
+        let events = run([first, last].concat(), None).await;
+
+        assert_eq!(events.complete.len(), 1);
+        assert_eq!(events.complete[0].merkle_chain, MerkleChainStatus::Broken);
+    }
+
+    fn link(merkle_root: u8, chained_merkle_root: Option<u8>, next: u32) -> ChainLink {
+        ChainLink {
+            merkle_root: Hash::new_from_array([merkle_root; 32]),
+            chained_merkle_root: chained_merkle_root.map(|root| Hash::new_from_array([root; 32])),
+            next_fec_set_index: next,
+        }
+    }
+
+    #[test]
+    fn merkle_chain_links_in_any_order() {
+        let mut chain = MerkleChain::default();
+        chain.add(32, link(2, Some(1), 64));
+        chain.add(64, link(3, Some(2), 96));
+        chain.add(0, link(1, Some(9), 32));
+        assert_eq!(chain.status(), MerkleChainStatus::Verified);
+    }
+
+    #[test]
+    fn merkle_chain_break() {
+        let mut chain = MerkleChain::default();
+        chain.add(0, link(1, Some(9), 32));
+        chain.add(64, link(3, Some(2), 96));
+        assert_eq!(chain.status(), MerkleChainStatus::Verified);
+
+        chain.add(32, link(2, Some(5), 64));
+        assert_eq!(chain.status(), MerkleChainStatus::Broken);
+    }
+
+    #[test]
+    fn merkle_chain_missing_chained_root() {
+        let mut chain = MerkleChain::default();
+        chain.add(0, link(1, None, 32));
+        // The first FEC set chains to the previous slot, which is not checked
+        assert_eq!(chain.status(), MerkleChainStatus::Verified);
+
+        chain.add(32, link(2, None, 64));
+        assert_eq!(chain.status(), MerkleChainStatus::Unknown);
+
+        // A break outweighs the unknown link
+        chain.add(64, link(3, Some(9), 96));
+        assert_eq!(chain.status(), MerkleChainStatus::Broken);
+    }
+
+    fn slot_complete() -> SlotCompleteEvent {
+        SlotCompleteEvent {
+            slot: SLOT,
+            data_shreds: 1,
+            recovered_shreds: 0,
+            entries: 0,
+            transactions: 0,
+            first_shred_received_at_micros: None,
+            last_shred_received_at_micros: None,
+            merkle_chain: MerkleChainStatus::Verified,
+        }
+    }
+
+    #[test]
+    fn slot_completes_once_every_batch_is_decoded() {
+        let progress = SlotProgress::new();
+        // Two dispatched batches, then the dispatcher's own count
+        progress.pending.fetch_add(2, Ordering::Relaxed);
+        let _ = progress.complete.set(slot_complete());
+        assert!(progress.release().is_none());
+        assert!(!progress.settled());
+
+        progress.transactions.fetch_add(3, Ordering::Relaxed);
+        assert!(progress.release().is_none());
+        let complete = progress.release().unwrap();
+
+        assert_eq!(complete.transactions, 3);
+        assert!(progress.settled());
+        assert!(progress.decoded());
+    }
+
+    #[test]
+    fn failed_batch_keeps_slot_incomplete() {
+        let progress = SlotProgress::new();
+        progress.pending.fetch_add(2, Ordering::Relaxed);
+        let _ = progress.complete.set(slot_complete());
+        assert!(progress.release().is_none());
+
+        progress.fail_batch();
+        assert!(progress.release().is_none());
+        assert!(progress.settled());
+        assert!(!progress.decoded());
+        assert_eq!(progress.failed_batches(), 1);
+    }
+
+    fn completed_fec_set(shreds: &[Shred]) -> CompletedFecSet {
+        CompletedFecSet {
+            slot: shreds[0].slot(),
+            fec_set_index: shreds[0].fec_set_index(),
+            merkle_root: merkle_root(shreds),
+            chained_merkle_root: shreds[0].chained_merkle_root().ok(),
+            data_shreds: shreds
+                .iter()
+                .filter(|shred| shred.is_data())
+                .map(|shred| {
+                    let shred_meta = ShredMeta {
+                        shred: shred.clone(),
+                        received_at_micros: None,
+                    };
+                    (shred.index(), shred_meta)
+                })
+                .collect(),
+            recovered_shreds: 0,
+            race: FecSetRace::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn late_fec_set_does_not_complete_evicted_slot_again() {
+        let shared = FecChannels::new(&UnshredConfig::default(), None)
+            .unwrap()
+            .shared;
+        let (batch_sender, mut batch_receiver) = stage::channel::<BatchWork>(
+            "batch_worker",
+            &Default::default(),
+            16,
+            BackpressurePolicy::DropNewest,
+        );
+        let batch_senders = [batch_sender];
+        let processor = ShredProcessor::new();
+        let mut slot_accumulators = HashMap::new();
+        let mut completed_slots = CompletedSlots::default();
+        let mut next_worker = 0;
+        let shreds = fec_set(&Keypair::new(), SLOT, 0, 2, true, Hash::default());
+
+        let complete = processor
+            .accumulate_completed_fec_set(
+                completed_fec_set(&shreds),
+                &mut slot_accumulators,
+                &mut completed_slots,
+                &batch_senders,
+                &mut next_worker,
+                &shared,
+            )
+            .await
+            .unwrap();
+        assert!(complete.is_none());
+        let mut batch_work = batch_receiver.recv().await.unwrap();
+        batch_work.decoded = true;
+        assert_eq!(batch_work.progress.release().unwrap().slot, SLOT);
+
+        let accumulator = slot_accumulators.get_mut(&SLOT).unwrap();
+        accumulator.created_at -= Duration::from_secs(31);
+        let evicted = ShredProcessor::cleanup_memory(&mut slot_accumulators).unwrap();
+        assert_eq!(evicted.len(), 1);
+
+        // The same FEC set again, e.g. from a slow feed
+        let complete = processor
+            .accumulate_completed_fec_set(
+                completed_fec_set(&shreds),
+                &mut slot_accumulators,
+                &mut completed_slots,
+                &batch_senders,
+                &mut next_worker,
+                &shared,
+            )
+            .await
+            .unwrap();
+        assert!(complete.is_none());
+        assert!(slot_accumulators.is_empty());
+        let [batch_sender] = batch_senders;
+        drop(batch_sender);
+        assert!(batch_receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn incomplete_fec_set_is_reported_by_its_largest_bucket() {
+        let genuine = fec_set(&Keypair::new(), SLOT, 0, 2, true, Hash::default());
+        let spoofed = fec_set(&Keypair::new(), SLOT, 0, 3, true, Hash::default());
+        let shreds = [
+            &spoofed[3..5],
+            &genuine[..3],
+            &genuine[4..6],
+            &spoofed[5..6],
+        ]
+        .concat();
+        let events = run(shreds, None).await;
+
+        assert!(events.complete.is_empty());
+        assert_eq!(events.incomplete.len(), 1);
+        let incomplete = &events.incomplete[0];
+        assert_eq!(incomplete.slot, SLOT);
+        assert_eq!(incomplete.incomplete_fec_sets.len(), 1);
+        assert_eq!(
+            incomplete.incomplete_fec_sets[0].data_shreds,
+            vec![0, 1, 2, 4, 5]
+        );
+        assert_eq!(incomplete.missing_data_shreds, vec![3]);
+    }
+
+    #[test]
+    fn completed_slots_are_bounded() {
+        let mut completed_slots = CompletedSlots::default();
+        for slot in 0..=MAX_COMPLETED_SLOTS as u64 {
+            completed_slots.insert(slot);
+        }
+        assert!(!completed_slots.contains(0));
+        assert!(completed_slots.contains(1));
+        assert!(completed_slots.contains(MAX_COMPLETED_SLOTS as u64));
+    }
+}
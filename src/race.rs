@@ -0,0 +1,195 @@
+use crate::events::{FeedRaceStats, SlotRaceEvent};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use ahash::HashMap;
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// Arrivals of one feed within a single FEC set
+#[derive(Debug, Clone, Copy, Default)]
+struct FeedArrivals {
+    first_received_at_micros: Option<u64>,
+    shreds: u32,
+    redundant: u32,
+}
+
+/// Tracks which feeds delivered shreds of a FEC set, and when
+#[derive(Debug, Clone, Default)]
+pub struct FecSetRace {
+    feeds: HashMap<Arc<str>, FeedArrivals>,
+}
+
+impl FecSetRace {
+    /// `redundant` - the FEC set already had a shred at this index
+    pub fn record(&mut self, feed: &Arc<str>, received_at_micros: Option<u64>, redundant: bool) {
+        let arrivals = self.feeds.entry(Arc::clone(feed)).or_default();
+        if redundant {
+            arrivals.redundant += 1;
+        } else {
+            arrivals.shreds += 1;
+        }
+
+        if let Some(received_at) = received_at_micros {
+            arrivals.first_received_at_micros = Some(
+                arrivals
+                    .first_received_at_micros
+                    .map_or(received_at, |first| first.min(received_at)),
+            );
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .feed_shreds
+                .with_label_values(&[&feed[..], if redundant { "redundant" } else { "unique" }])
+                .inc();
+        }
+    }
+
+    /// Feed that delivered the earliest shred and when it arrived
+    fn winner(&self) -> Option<(&Arc<str>, u64)> {
+        self.feeds
+            .iter()
+            .filter_map(|(feed, arrivals)| arrivals.first_received_at_micros.map(|ts| (feed, ts)))
+            .min_by_key(|(_, ts)| *ts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FeedTotals {
+    fec_sets_first: u32,
+    shreds: u32,
+    redundant: u32,
+    late: u32,
+    gap_sum_micros: u64,
+    gap_count: u32,
+    max_gap_micros: u64,
+}
+
+/// Per-feed race totals across all completed FEC sets of a slot
+#[derive(Debug, Default)]
+pub struct SlotRace {
+    fec_sets: u32,
+    first: Option<(Arc<str>, u64)>,
+    feeds: HashMap<Arc<str>, FeedTotals>,
+}
+
+impl SlotRace {
+    pub fn add_fec_set(&mut self, race: &FecSetRace) {
+        self.fec_sets += 1;
+        let winner = race.winner();
+
+        if let Some((feed, received_at)) = winner {
+            if self
+                .first
+                .as_ref()
+                .is_none_or(|(_, first)| received_at < *first)
+            {
+                self.first = Some((Arc::clone(feed), received_at));
+            }
+        }
+
+        for (feed, arrivals) in &race.feeds {
+            let totals = self.feeds.entry(Arc::clone(feed)).or_default();
+            totals.shreds += arrivals.shreds;
+            totals.redundant += arrivals.redundant;
+
+            let Some((winner_feed, winner_received_at)) = winner else {
+                continue;
+            };
+            if winner_feed == feed {
+                totals.fec_sets_first += 1;
+
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .feed_fec_sets_first
+                        .with_label_values(&[&feed[..]])
+                        .inc();
+                }
+            }
+
+            // How far this feed trailed the fastest one on this FEC set
+            if let Some(first) = arrivals.first_received_at_micros {
+                let gap = first.saturating_sub(winner_received_at);
+                totals.gap_sum_micros += gap;
+                totals.gap_count += 1;
+                totals.max_gap_micros = totals.max_gap_micros.max(gap);
+
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .feed_latency_gap
+                        .with_label_values(&[&feed[..]])
+                        .observe(gap as f64 / 1_000_000.0);
+                }
+            }
+        }
+    }
+
+    pub fn add_late(&mut self, late: HashMap<Arc<str>, u32>) {
+        for (feed, count) in late {
+            self.feeds.entry(feed).or_default().late += count;
+        }
+    }
+
+    pub fn into_event(self, slot: u64) -> SlotRaceEvent {
+        let mut feeds: Vec<FeedRaceStats> = self
+            .feeds
+            .into_iter()
+            .map(|(feed, totals)| FeedRaceStats {
+                feed,
+                fec_sets_first: totals.fec_sets_first,
+                shreds: totals.shreds,
+                redundant: totals.redundant,
+                late: totals.late,
+                mean_gap_micros: (totals.gap_count > 0)
+                    .then(|| totals.gap_sum_micros / totals.gap_count as u64),
+                max_gap_micros: (totals.gap_count > 0).then_some(totals.max_gap_micros),
+            })
+            .collect();
+        feeds.sort_unstable_by_key(|feed| std::cmp::Reverse(feed.fec_sets_first));
+
+        SlotRaceEvent {
+            slot,
+            fec_sets: self.fec_sets,
+            first_feed: self.first.map(|(feed, _)| feed),
+            feeds,
+        }
+    }
+}
+
+/// Shreds per (slot, feed) that arrived after their FEC set was already complete
+#[derive(Debug, Default)]
+pub struct LateShreds {
+    counts: DashMap<u64, HashMap<Arc<str>, u32>>,
+}
+
+impl LateShreds {
+    pub fn record(&self, slot: u64, feed: &Arc<str>) {
+        *self
+            .counts
+            .entry(slot)
+            .or_default()
+            .entry(Arc::clone(feed))
+            .or_insert(0) += 1;
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .feed_shreds
+                .with_label_values(&[&feed[..], "late"])
+                .inc();
+        }
+    }
+
+    pub fn take(&self, slot: u64) -> Option<HashMap<Arc<str>, u32>> {
+        self.counts.remove(&slot).map(|(_, late)| late)
+    }
+
+    /// Drops counts for slots that will no longer be reported
+    pub fn prune_below(&self, slot: u64) {
+        self.counts.retain(|s, _| *s >= slot);
+    }
+}
@@ -1,34 +1,98 @@
 #[cfg(feature = "metrics")]
 use crate::metrics::Metrics;
-use crate::types::ShredBytesMeta;
+use crate::{
+    config::{MulticastConfig, TimestampSource},
+    source::{ShredSource, SourceShred},
+};
 
 use anyhow::Result;
-use dashmap::DashSet;
 use socket2::{Domain, Socket, Type};
+#[cfg(feature = "metrics")]
+use std::time::Instant;
 use std::{
-    mem::MaybeUninit,
     net::SocketAddr,
     sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::{sync::mpsc::Sender, task};
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+use tracing::warn;
 use tracing::{error, info};
 
+mod multicast;
+#[cfg(target_os = "linux")]
+mod timestamp;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod uring;
+
 const SHRED_SIZE: usize = 1228;
 const RECV_BUFFER_SIZE: usize = 64 * 1024 * 1024; // 64MB
-const OFFSET_SHRED_SLOT: usize = 65;
-const OFFSET_FEC_SET_INDEX: usize = 79;
+pub const DEFAULT_RECV_BATCH_SIZE: usize = 64;
 
 pub struct ShredReceiver {
-    socket: Arc<Socket>,
+    name: Arc<str>,
+    sockets: Vec<Socket>,
+    recv_batch_size: usize,
+    timestamp_source: TimestampSource,
 }
 
 impl ShredReceiver {
-    pub fn new(bind_addr: SocketAddr) -> Result<Self> {
-        // UDP socket
-        let socket = Socket::new(Domain::IPV4, Type::DGRAM, None)?;
+    pub fn new(
+        name: &str,
+        bind_addr: SocketAddr,
+        num_receivers: usize,
+        recv_batch_size: usize,
+        timestamp_source: TimestampSource,
+        multicast: Option<&MulticastConfig>,
+    ) -> Result<Self> {
+        // SO_REUSEPORT doesn't shard multicast, every socket would get every datagram
+        let num_receivers = match multicast {
+            Some(_) if num_receivers > 1 => {
+                tracing::warn!(
+                    "Multicast input {} uses 1 receiver instead of {}",
+                    name,
+                    num_receivers
+                );
+                1
+            }
+            _ => num_receivers,
+        };
+
+        // One socket per receiver, kernel shards packets across them via SO_REUSEPORT
+        let sockets = (0..num_receivers)
+            .map(|_| Self::bind_socket(bind_addr, timestamp_source, multicast))
+            .collect::<Result<Vec<_>>>()?;
+        info!(
+            "UDP receiver {} bound {} sockets to {}, {:?} timestamps",
+            name,
+            sockets.len(),
+            bind_addr,
+            timestamp_source
+        );
+
+        Ok(Self {
+            name: Arc::from(name),
+            sockets,
+            recv_batch_size: std::cmp::max(recv_batch_size, 1),
+            timestamp_source,
+        })
+    }
+
+    fn bind_socket(
+        bind_addr: SocketAddr,
+        timestamp_source: TimestampSource,
+        multicast: Option<&MulticastConfig>,
+    ) -> Result<Socket> {
+        // UDP socket, address family follows the bind address
+        let socket = Socket::new(Domain::for_address(bind_addr), Type::DGRAM, None)?;
+
+        // `[::]` binds dual-stack so IPv4 feeds arrive as v4-mapped addresses,
+        // a specific IPv6 address only accepts IPv6
+        if let SocketAddr::V6(addr) = bind_addr {
+            socket.set_only_v6(!addr.ip().is_unspecified())?;
+        }
 
         socket.set_reuse_address(true)?;
+        socket.set_reuse_port(true)?;
         socket.set_recv_buffer_size(RECV_BUFFER_SIZE)?;
         socket.set_nonblocking(true)?;
 
@@ -52,167 +116,85 @@ impl ShredReceiver {
             }
         }
 
-        socket.bind(&bind_addr.into())?;
-        info!("UDP receiver bound to {}", bind_addr);
-
-        Ok(Self {
-            socket: Arc::new(socket),
-        })
-    }
-
-    pub async fn run(
-        self,
-        senders: Vec<Sender<ShredBytesMeta>>,
-        processed_fec_sets: Arc<DashSet<(u64, u32)>>,
-    ) -> Result<()> {
-        // Spawn receiver threads
-        let num_receivers = 1;
-        info!("Starting {} network receiver workers", num_receivers);
-        let mut handles = Vec::with_capacity(num_receivers);
-
-        for i in 0..num_receivers {
-            let socket = Arc::clone(&self.socket);
-            let senders = senders.clone();
-            let processed_fec_sets = Arc::clone(&processed_fec_sets);
-
-            let handle = task::spawn_blocking(move || {
-                if let Err(e) = Self::receive_loop(socket, senders, processed_fec_sets) {
-                    error!("Reciever {} failed: {}", i, e);
-                }
-            });
-            handles.push(handle);
+        // Arrival time from the kernel, before the datagram queues in the socket buffer
+        #[cfg(target_os = "linux")]
+        timestamp::enable(&socket, timestamp_source)?;
+        #[cfg(not(target_os = "linux"))]
+        if timestamp_source != TimestampSource::System {
+            return Err(anyhow::anyhow!(
+                "{:?} timestamps are only supported on Linux",
+                timestamp_source
+            ));
         }
 
-        for handle in handles {
-            handle.await?;
+        socket.bind(&bind_addr.into())?;
+
+        if let Some(multicast) = multicast {
+            multicast::join(&socket, multicast)?;
+            info!(
+                "Joined multicast groups {:?} on {}",
+                multicast.groups, bind_addr
+            );
         }
 
-        Ok(())
+        Ok(socket)
     }
 
-    fn receive_loop(
-        socket: Arc<Socket>,
-        senders: Vec<Sender<ShredBytesMeta>>,
-        processed_fec_sets: Arc<DashSet<(u64, u32)>>,
-    ) -> Result<()> {
-        #[cfg(feature = "metrics")]
-        let mut last_channel_update = std::time::Instant::now();
-        // Pre-allocate buffer
-        let mut buffer = vec![MaybeUninit::<u8>::uninit(); SHRED_SIZE];
+    /// One `ShredSource` per socket, fed by io_uring or `recvmmsg`
+    pub fn into_sources(self) -> Vec<UdpSource> {
+        let kernel_timestamps = self.timestamp_source != TimestampSource::System;
 
-        loop {
-            match socket.recv(&mut buffer) {
-                Ok(size) if size > 0 => {
-                    let received_at_micros = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_micros() as u64;
-
-                    // SAFETY: socket.recv() guarantees the first `size` bytes are initialized
-                    let initialized_data =
-                        unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, size) };
-
-                    if let Err(e) = Self::process_shred(
-                        initialized_data,
-                        &senders,
-                        &processed_fec_sets,
-                        &received_at_micros,
-                    ) {
-                        error!("Receiver failed to process shred: {}", e);
-                        #[cfg(feature = "metrics")]
-                        if let Some(metrics) = Metrics::try_get() {
-                            metrics
-                                .errors
-                                .with_label_values(&["receiver", "process_shred"])
-                                .inc();
-                        }
-                    }
-                }
-                Ok(_) => continue,
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    continue;
-                }
-                Err(e) => {
-                    error!("Socket receive error: {}", e);
+        self.sockets
+            .into_iter()
+            .enumerate()
+            .map(|(i, socket)| {
+                let receiver_name = format!("{}-{}", self.name, i);
+                let batch = Self::packet_batch(
+                    &receiver_name,
+                    &socket,
+                    self.recv_batch_size,
+                    kernel_timestamps,
+                );
+                UdpSource {
+                    feed: Arc::clone(&self.name),
+                    receiver_name,
+                    socket,
+                    batch,
+                    count: 0,
+                    next: 0,
+                    batch_received_at_micros: 0,
                     #[cfg(feature = "metrics")]
-                    if let Some(metrics) = Metrics::try_get() {
-                        metrics
-                            .errors
-                            .with_label_values(&["receiver", "socket_receive"])
-                            .inc();
-                    }
-                    std::thread::sleep(Duration::from_millis(1));
-                }
-            }
-
-            // Update metrics periodically
-            #[cfg(feature = "metrics")]
-            if last_channel_update.elapsed() > Duration::from_secs(1) {
-                if let Ok((buf_used, buf_size)) = Self::get_socket_buffer_stats(&socket) {
-                    if buf_size > 0 {
-                        let utilization = (buf_used as f64 / buf_size as f64) * 100.0;
-                        if let Some(metrics) = Metrics::try_get() {
-                            metrics
-                                .receiver_socket_buffer_utilization
-                                .with_label_values(&["receiver"])
-                                .set(utilization as i64)
-                        }
-                    }
+                    last_channel_update: Instant::now(),
                 }
-
-                last_channel_update = std::time::Instant::now();
-            }
-        }
+            })
+            .collect()
     }
 
-    /// Creates ShredBytesMeta and sends through `senders`
-    fn process_shred(
-        buffer: &[u8],
-        senders: &[Sender<ShredBytesMeta>],
-        processed_fec_sets: &DashSet<(u64, u32)>,
-        received_at_micros: &u64,
-    ) -> Result<()> {
-        if buffer.len() < 88 {
-            // Minimum shred header size
-            return Err(anyhow::anyhow!("Invalid shred size"));
-        }
-
-        #[cfg(feature = "metrics")]
-        if let Some(metrics) = Metrics::try_get() {
-            metrics
-                .receiver_shreds_received
-                .with_label_values(&["raw"])
-                .inc();
-        }
-
-        // Parse shred header
-        let slot = u64::from_le_bytes(buffer[OFFSET_SHRED_SLOT..OFFSET_SHRED_SLOT + 8].try_into()?);
-        let fec_set_index =
-            u32::from_le_bytes(buffer[OFFSET_FEC_SET_INDEX..OFFSET_FEC_SET_INDEX + 4].try_into()?);
-
-        let fec_key = (slot, fec_set_index);
-        if processed_fec_sets.contains(&fec_key) {
-            return Ok(()); // Exit early
-        }
-
-        // Send ShredBytesMeta to processor
-        let worker_id = (fec_set_index as usize) % senders.len();
-        let sender = &senders[worker_id];
-        let shred_bytes_meta = ShredBytesMeta {
-            shred_bytes: Arc::new(buffer.to_vec()),
-            received_at_micros: Some(*received_at_micros),
-        };
-        match sender.try_send(shred_bytes_meta) {
-            Ok(_) => {}
-            Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
-                return Err(anyhow::anyhow!("Channel full, backpressure detected"));
+    #[cfg_attr(
+        not(all(feature = "io_uring", target_os = "linux")),
+        allow(unused_variables)
+    )]
+    fn packet_batch(
+        receiver_name: &str,
+        socket: &Socket,
+        recv_batch_size: usize,
+        kernel_timestamps: bool,
+    ) -> Box<dyn PacketBatch + Send> {
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        match uring::UringBatch::new(socket, recv_batch_size, kernel_timestamps) {
+            Ok(batch) => {
+                info!("Receiver {} using io_uring backend", receiver_name);
+                return Box::new(batch);
             }
-            Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
-                return Err(anyhow::anyhow!("Channel disconnected"));
+            Err(e) => {
+                warn!(
+                    "Receiver {} could not set up io_uring, falling back: {}",
+                    receiver_name, e
+                );
             }
-        };
+        }
 
-        Ok(())
+        Box::new(RecvBatch::new(recv_batch_size, kernel_timestamps))
     }
 
     #[cfg(feature = "metrics")]
@@ -248,3 +230,380 @@ impl ShredReceiver {
         Ok((0, 0))
     }
 }
+
+/// UDP socket of a `ShredReceiver`, pulled one datagram at a time from batched receives
+pub struct UdpSource {
+    feed: Arc<str>,
+    receiver_name: String,
+    socket: Socket,
+    batch: Box<dyn PacketBatch + Send>,
+    count: usize, // Datagrams in the current batch
+    next: usize,
+    batch_received_at_micros: u64,
+    #[cfg(feature = "metrics")]
+    last_channel_update: Instant,
+}
+
+impl UdpSource {
+    fn recv_batch(&mut self) {
+        self.count = 0;
+        self.next = 0;
+
+        match self.batch.recv(&self.socket) {
+            Ok(count) if count > 0 => {
+                // Fallback for datagrams without a kernel timestamp
+                self.batch_received_at_micros = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_micros() as u64;
+                self.count = count;
+
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .receiver_packets_received
+                        .with_label_values(&[&self.receiver_name])
+                        .inc_by(count as u64);
+                    metrics
+                        .receiver_batch_size
+                        .with_label_values(&[&self.receiver_name])
+                        .observe(count as f64);
+                }
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(e) => {
+                error!(
+                    "Receiver {} socket receive error: {}",
+                    self.receiver_name, e
+                );
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .errors
+                        .with_label_values(&["receiver", "socket_receive"])
+                        .inc();
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        // Update metrics periodically
+        #[cfg(feature = "metrics")]
+        if self.last_channel_update.elapsed() > Duration::from_secs(1) {
+            if let Ok((buf_used, buf_size)) = ShredReceiver::get_socket_buffer_stats(&self.socket) {
+                if buf_size > 0 {
+                    let utilization = (buf_used as f64 / buf_size as f64) * 100.0;
+                    if let Some(metrics) = Metrics::try_get() {
+                        metrics
+                            .receiver_socket_buffer_utilization
+                            .with_label_values(&[&self.receiver_name])
+                            .set(utilization as i64)
+                    }
+                }
+            }
+
+            self.last_channel_update = Instant::now();
+        }
+    }
+}
+
+impl ShredSource for UdpSource {
+    fn name(&self) -> &str {
+        &self.feed
+    }
+
+    /// Never exhausted, receive errors are logged and retried
+    fn next_shred(&mut self) -> Result<Option<SourceShred<'_>>> {
+        loop {
+            while self.next < self.count {
+                let i = self.next;
+                self.next += 1;
+                if self.batch.packet(i).is_empty() {
+                    continue;
+                }
+
+                let (received_at_micros, _clock) = self
+                    .batch
+                    .received_at(i)
+                    .unwrap_or((self.batch_received_at_micros, Clock::System));
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .receiver_timestamps
+                        .with_label_values(&[&self.receiver_name, _clock.as_str()])
+                        .inc();
+                }
+
+                return Ok(Some(SourceShred {
+                    bytes: self.batch.packet(i),
+                    received_at_micros: Some(received_at_micros),
+                    peer_addr: self.batch.peer_addr(i),
+                    feed: None,
+                }));
+            }
+
+            self.recv_batch();
+        }
+    }
+}
+
+/// Datagrams of a single receive call
+trait PacketBatch {
+    /// Receives the next batch and returns the number of datagrams in it
+    fn recv(&mut self, socket: &Socket) -> std::io::Result<usize>;
+
+    /// Payload of the `i`th datagram of the last batch
+    fn packet(&self, i: usize) -> &[u8];
+
+    /// Sender of the `i`th datagram of the last batch
+    fn peer_addr(&self, i: usize) -> Option<SocketAddr>;
+
+    /// Kernel arrival time of the `i`th datagram of the last batch, in micros
+    fn received_at(&self, _i: usize) -> Option<(u64, Clock)> {
+        None
+    }
+}
+
+/// Clock a datagram's arrival time was read from
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    System,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Kernel,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Hardware,
+}
+
+impl Clock {
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Kernel => "kernel",
+            Self::Hardware => "hardware",
+        }
+    }
+}
+
+/// Converts a kernel-filled socket address, unmapping v4-mapped IPv6 peers
+#[cfg(unix)]
+fn socket_addr_from_raw(
+    storage: &libc::sockaddr_storage,
+    len: libc::socklen_t,
+) -> Option<SocketAddr> {
+    // SAFETY: the kernel initialized `len` bytes of `storage`
+    let addr = unsafe { socket2::SockAddr::new(*storage, len) }.as_socket()?;
+    Some(SocketAddr::new(addr.ip().to_canonical(), addr.port()))
+}
+
+/// Preallocated buffer ring filled by a single `recvmmsg` call
+#[cfg(target_os = "linux")]
+struct RecvBatch {
+    buffers: Vec<u8>,
+    _iovecs: Vec<libc::iovec>, // Referenced by `headers`
+    addrs: Vec<libc::sockaddr_storage>,
+    controls: Vec<u8>, // Timestamp control messages, empty unless enabled
+    control_size: usize,
+    headers: Vec<libc::mmsghdr>,
+}
+
+// SAFETY: the raw pointers in `headers` only reference heap buffers owned by the batch,
+// which don't move with it
+#[cfg(target_os = "linux")]
+unsafe impl Send for RecvBatch {}
+
+#[cfg(target_os = "linux")]
+impl RecvBatch {
+    fn new(batch_size: usize, timestamps: bool) -> Self {
+        let mut buffers = vec![0u8; batch_size * SHRED_SIZE];
+        let control_size = if timestamps {
+            timestamp::CONTROL_SIZE
+        } else {
+            0
+        };
+        let mut controls = vec![0u8; batch_size * control_size];
+        let mut iovecs: Vec<libc::iovec> = buffers
+            .chunks_exact_mut(SHRED_SIZE)
+            .map(|chunk| libc::iovec {
+                iov_base: chunk.as_mut_ptr() as *mut libc::c_void,
+                iov_len: SHRED_SIZE,
+            })
+            .collect();
+        // SAFETY: sockaddr_storage is a plain C struct, all-zero is valid
+        let mut addrs = vec![unsafe { std::mem::zeroed::<libc::sockaddr_storage>() }; batch_size];
+        let headers = iovecs
+            .iter_mut()
+            .zip(addrs.iter_mut())
+            .enumerate()
+            .map(|(i, (iovec, addr))| {
+                // SAFETY: mmsghdr is a plain C struct, all-zero is a valid empty header
+                let mut header: libc::mmsghdr = unsafe { std::mem::zeroed() };
+                header.msg_hdr.msg_iov = iovec as *mut libc::iovec;
+                header.msg_hdr.msg_iovlen = 1;
+                header.msg_hdr.msg_name = addr as *mut _ as *mut libc::c_void;
+                if control_size > 0 {
+                    header.msg_hdr.msg_control =
+                        controls[i * control_size..].as_mut_ptr() as *mut libc::c_void;
+                }
+                header
+            })
+            .collect();
+
+        Self {
+            buffers,
+            _iovecs: iovecs,
+            addrs,
+            controls,
+            control_size,
+            headers,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PacketBatch for RecvBatch {
+    fn recv(&mut self, socket: &Socket) -> std::io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        // The kernel overwrites name and control lengths with the actual sizes
+        for header in &mut self.headers {
+            header.msg_hdr.msg_namelen =
+                std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            header.msg_hdr.msg_controllen = self.control_size as _;
+        }
+
+        // SAFETY: every header points to an iovec backed by `buffers`, all owned by self
+        let received = unsafe {
+            libc::recvmmsg(
+                socket.as_raw_fd(),
+                self.headers.as_mut_ptr(),
+                self.headers.len() as libc::c_uint,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if received < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(received as usize)
+    }
+
+    fn packet(&self, i: usize) -> &[u8] {
+        let size = std::cmp::min(self.headers[i].msg_len as usize, SHRED_SIZE);
+        &self.buffers[i * SHRED_SIZE..i * SHRED_SIZE + size]
+    }
+
+    fn peer_addr(&self, i: usize) -> Option<SocketAddr> {
+        socket_addr_from_raw(&self.addrs[i], self.headers[i].msg_hdr.msg_namelen)
+    }
+
+    fn received_at(&self, i: usize) -> Option<(u64, Clock)> {
+        if self.control_size == 0 {
+            return None;
+        }
+        #[allow(clippy::unnecessary_cast)] // socklen_t on musl
+        let len = std::cmp::min(
+            self.headers[i].msg_hdr.msg_controllen as usize,
+            self.control_size,
+        );
+        let start = i * self.control_size;
+        timestamp::parse(&self.controls[start..start + len])
+    }
+}
+
+/// Single datagram fallback where `recvmmsg` is unavailable
+#[cfg(not(target_os = "linux"))]
+struct RecvBatch {
+    buffer: Vec<std::mem::MaybeUninit<u8>>,
+    size: usize,
+    peer_addr: Option<SocketAddr>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl RecvBatch {
+    fn new(_batch_size: usize, _timestamps: bool) -> Self {
+        Self {
+            buffer: vec![std::mem::MaybeUninit::<u8>::uninit(); SHRED_SIZE],
+            size: 0,
+            peer_addr: None,
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl PacketBatch for RecvBatch {
+    fn recv(&mut self, socket: &Socket) -> std::io::Result<usize> {
+        let (size, addr) = socket.recv_from(&mut self.buffer)?;
+        self.size = size;
+        self.peer_addr = addr
+            .as_socket()
+            .map(|addr| SocketAddr::new(addr.ip().to_canonical(), addr.port()));
+        Ok(1)
+    }
+
+    fn packet(&self, _i: usize) -> &[u8] {
+        // SAFETY: socket.recv() guarantees the first `size` bytes are initialized
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.size) }
+    }
+
+    fn peer_addr(&self, _i: usize) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, UdpSocket};
+
+    const GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 77, 77);
+
+    #[test]
+    #[ignore = "needs multicast on the loopback interface, run with --ignored"]
+    fn multicast_loopback() {
+        let config = MulticastConfig {
+            groups: vec![GROUP.into()],
+            interface: Some("lo".to_string()),
+            source: None,
+        };
+        let bind_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0));
+        let receiver = ShredReceiver::new(
+            "multicast",
+            bind_addr,
+            1,
+            DEFAULT_RECV_BATCH_SIZE,
+            TimestampSource::System,
+            Some(&config),
+        )
+        .expect("cannot join the multicast group on lo");
+        let port = receiver.sockets[0]
+            .local_addr()
+            .unwrap()
+            .as_socket()
+            .unwrap()
+            .port();
+
+        let sender = Socket::from(UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap());
+        sender.set_multicast_loop_v4(true).unwrap();
+        sender.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+        sender
+            .send_to(b"multicast shred", &SocketAddr::from((GROUP, port)).into())
+            .expect("cannot send to the multicast group on lo");
+
+        let mut source = receiver.into_sources().pop().unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        // `next_shred` spins until a datagram arrives
+        std::thread::spawn(move || {
+            let shred = source.next_shred().unwrap().unwrap();
+            tx.send((shred.bytes.to_vec(), shred.peer_addr)).unwrap();
+        });
+        let (bytes, peer_addr) = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("multicast datagram not received");
+
+        assert_eq!(bytes, b"multicast shred");
+        assert_eq!(peer_addr, sender.local_addr().unwrap().as_socket());
+    }
+}
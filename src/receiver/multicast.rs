@@ -0,0 +1,145 @@
+use crate::config::MulticastConfig;
+
+use anyhow::Result;
+use socket2::{InterfaceIndexOrAddress, Socket};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Interface the groups are joined on
+#[derive(Clone, Copy)]
+enum Interface {
+    Any,
+    Index(u32),
+    Address(Ipv4Addr),
+}
+
+impl Interface {
+    fn resolve(interface: Option<&str>) -> Result<Self> {
+        let Some(interface) = interface else {
+            return Ok(Self::Any);
+        };
+        if let Ok(addr) = interface.parse::<Ipv4Addr>() {
+            return Ok(Self::Address(addr));
+        }
+
+        let name = std::ffi::CString::new(interface)?;
+        // SAFETY: `name` is a valid NUL-terminated string
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index == 0 {
+            return Err(anyhow::anyhow!("Unknown multicast interface {}", interface));
+        }
+
+        Ok(Self::Index(index))
+    }
+
+    fn index(&self) -> Result<u32> {
+        match self {
+            Self::Any => Ok(0),
+            Self::Index(index) => Ok(*index),
+            Self::Address(addr) => Err(anyhow::anyhow!(
+                "Multicast interface {} must be given by name for this group",
+                addr
+            )),
+        }
+    }
+}
+
+/// Joins every configured group on `socket`
+pub(super) fn join(socket: &Socket, multicast: &MulticastConfig) -> Result<()> {
+    let interface = Interface::resolve(multicast.interface.as_deref())?;
+
+    // Only deliver groups joined on this socket, not every group on the port
+    #[cfg(target_os = "linux")]
+    match socket.local_addr().ok().and_then(|addr| addr.as_socket()) {
+        Some(addr) if addr.is_ipv4() => socket.set_multicast_all_v4(false)?,
+        Some(_) => socket.set_multicast_all_v6(false)?,
+        None => {}
+    }
+
+    for group in &multicast.groups {
+        match (*group, multicast.source) {
+            (IpAddr::V4(group), None) => {
+                let interface = match interface {
+                    Interface::Any => InterfaceIndexOrAddress::Index(0),
+                    Interface::Index(index) => InterfaceIndexOrAddress::Index(index),
+                    Interface::Address(addr) => InterfaceIndexOrAddress::Address(addr),
+                };
+                socket.join_multicast_v4_n(&group, &interface)?;
+            }
+            (IpAddr::V6(group), None) => {
+                socket.join_multicast_v6(&group, interface.index()?)?;
+            }
+            (IpAddr::V4(group), Some(IpAddr::V4(source))) => match interface {
+                Interface::Any => socket.join_ssm_v4(&source, &group, &Ipv4Addr::UNSPECIFIED)?,
+                Interface::Address(addr) => socket.join_ssm_v4(&source, &group, &addr)?,
+                Interface::Index(index) => {
+                    join_source_group(socket, index, group.into(), source.into())?
+                }
+            },
+            (IpAddr::V6(group), Some(IpAddr::V6(source))) => {
+                join_source_group(socket, interface.index()?, group.into(), source.into())?
+            }
+            (group, Some(source)) => {
+                return Err(anyhow::anyhow!(
+                    "Multicast source {} and group {} differ in address family",
+                    source,
+                    group
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Source-specific join by interface index (`MCAST_JOIN_SOURCE_GROUP`)
+#[cfg(target_os = "linux")]
+fn join_source_group(socket: &Socket, interface: u32, group: IpAddr, source: IpAddr) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // `struct group_source_req` from <netinet/in.h>, not exposed by libc
+    #[repr(C)]
+    struct GroupSourceReq {
+        gsr_interface: u32,
+        gsr_group: libc::sockaddr_storage,
+        gsr_source: libc::sockaddr_storage,
+    }
+
+    let level = match group {
+        IpAddr::V4(_) => libc::IPPROTO_IP,
+        IpAddr::V6(_) => libc::IPPROTO_IPV6,
+    };
+    let req = GroupSourceReq {
+        gsr_interface: interface,
+        gsr_group: socket2::SockAddr::from(std::net::SocketAddr::new(group, 0)).as_storage(),
+        gsr_source: socket2::SockAddr::from(std::net::SocketAddr::new(source, 0)).as_storage(),
+    };
+
+    // SAFETY: `req` matches the kernel's group_source_req layout
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            libc::MCAST_JOIN_SOURCE_GROUP,
+            &req as *const _ as *const libc::c_void,
+            std::mem::size_of::<GroupSourceReq>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn join_source_group(
+    _socket: &Socket,
+    _interface: u32,
+    group: IpAddr,
+    _source: IpAddr,
+) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Source-specific join of {} by interface index is only supported on Linux",
+        group
+    ))
+}
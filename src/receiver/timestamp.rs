@@ -0,0 +1,99 @@
+use super::Clock;
+use crate::config::TimestampSource;
+
+use anyhow::Result;
+use socket2::Socket;
+use std::{mem::size_of, os::unix::io::AsRawFd};
+
+const fn cmsg_align(len: usize) -> usize {
+    (len + size_of::<usize>() - 1) & !(size_of::<usize>() - 1)
+}
+
+/// Control buffer space per datagram, fits `SCM_TIMESTAMPING` (3 timespecs)
+pub(super) const CONTROL_SIZE: usize =
+    cmsg_align(size_of::<libc::cmsghdr>()) + cmsg_align(3 * size_of::<libc::timespec>());
+
+/// Asks the kernel to attach arrival timestamps to every datagram on `socket`
+pub(super) fn enable(socket: &Socket, source: TimestampSource) -> Result<()> {
+    let (option, value) = match source {
+        TimestampSource::System => return Ok(()),
+        TimestampSource::Kernel => (libc::SO_TIMESTAMPNS, 1),
+        // Software RX timestamps as well, for packets the NIC didn't stamp
+        TimestampSource::Hardware => (
+            libc::SO_TIMESTAMPING,
+            (libc::SOF_TIMESTAMPING_RX_HARDWARE
+                | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+                | libc::SOF_TIMESTAMPING_RX_SOFTWARE
+                | libc::SOF_TIMESTAMPING_SOFTWARE) as libc::c_int,
+        ),
+    };
+
+    // SAFETY: `value` is a c_int, as both options expect
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            option,
+            &value as *const _ as *const libc::c_void,
+            size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+/// Reads the arrival time from the control messages of one datagram
+pub(super) fn parse(control: &[u8]) -> Option<(u64, Clock)> {
+    let header_size = cmsg_align(size_of::<libc::cmsghdr>());
+    let mut offset = 0;
+
+    while offset + header_size <= control.len() {
+        // SAFETY: bounds checked above, control buffers carry no alignment guarantee
+        let header =
+            unsafe { std::ptr::read_unaligned(control[offset..].as_ptr() as *const libc::cmsghdr) };
+        let len = header.cmsg_len as usize;
+        if len < header_size || offset + len > control.len() {
+            return None;
+        }
+        let data = &control[offset + header_size..offset + len];
+
+        if header.cmsg_level == libc::SOL_SOCKET {
+            match header.cmsg_type {
+                libc::SCM_TIMESTAMPNS => {
+                    return read_timespec(data, 0).map(|micros| (micros, Clock::Kernel));
+                }
+                libc::SCM_TIMESTAMPING => {
+                    // [software, deprecated, raw hardware]
+                    if let Some(micros) = read_timespec(data, 2) {
+                        return Some((micros, Clock::Hardware));
+                    }
+                    return read_timespec(data, 0).map(|micros| (micros, Clock::Kernel));
+                }
+                _ => {}
+            }
+        }
+
+        offset += cmsg_align(len);
+    }
+
+    None
+}
+
+/// Micros since the epoch of the `index`th timespec in `data`, `None` if unset
+fn read_timespec(data: &[u8], index: usize) -> Option<u64> {
+    let start = index * size_of::<libc::timespec>();
+    if start + size_of::<libc::timespec>() > data.len() {
+        return None;
+    }
+
+    // SAFETY: bounds checked above
+    let ts = unsafe { std::ptr::read_unaligned(data[start..].as_ptr() as *const libc::timespec) };
+    if ts.tv_sec == 0 && ts.tv_nsec == 0 {
+        return None;
+    }
+
+    Some(ts.tv_sec as u64 * 1_000_000 + ts.tv_nsec as u64 / 1_000)
+}
@@ -0,0 +1,247 @@
+use super::{socket_addr_from_raw, timestamp, Clock, PacketBatch, SHRED_SIZE};
+
+use io_uring::{cqueue, opcode, squeue, types, IoUring};
+use socket2::Socket;
+use std::{net::SocketAddr, os::unix::io::AsRawFd};
+use tracing::error;
+
+const BUFFER_GROUP: u16 = 0;
+const USER_DATA_RECV: u64 = 1;
+const USER_DATA_PROVIDE: u64 = 2;
+
+// Provided buffers are prefixed with `struct io_uring_recvmsg_out` (4 x u32)
+const RECVMSG_OUT_SIZE: usize = 16;
+const NAME_SIZE: usize = std::mem::size_of::<libc::sockaddr_storage>();
+
+/// Multishot `recvmsg` into a pool of buffers provided to the ring.
+///
+/// Unlike the `recvmmsg` path this blocks in the kernel while the feed is idle
+/// instead of spinning on `WouldBlock`.
+pub(super) struct UringBatch {
+    ring: IoUring,
+    buffers: Vec<u8>,
+    buffer_size: usize,
+    num_buffers: u16,
+    msghdr: Box<libc::msghdr>, // Read by the kernel for every multishot completion
+    packets: Vec<(usize, usize)>, // (offset, len) into `buffers`
+    peer_addrs: Vec<Option<SocketAddr>>,
+    received_at: Vec<Option<(u64, Clock)>>,
+    used_buffers: Vec<u16>, // Returned to the ring on the next `recv`
+    cqes: Vec<(u64, i32, u32)>,
+    armed: bool,
+}
+
+// SAFETY: `msghdr` only holds lengths, the buffers it describes are provided to the ring
+unsafe impl Send for UringBatch {}
+
+impl UringBatch {
+    pub(super) fn new(
+        socket: &Socket,
+        batch_size: usize,
+        timestamps: bool,
+    ) -> std::io::Result<Self> {
+        // At least a full batch in flight, leave SQ room to return every buffer at once
+        let num_buffers = batch_size
+            .clamp(32, u16::MAX as usize / 2)
+            .next_power_of_two() as u16;
+        let ring = IoUring::new(num_buffers as u32 * 2)?;
+
+        // SAFETY: msghdr is a plain C struct, all-zero is a valid empty header
+        let mut msghdr: Box<libc::msghdr> = Box::new(unsafe { std::mem::zeroed() });
+        msghdr.msg_namelen = NAME_SIZE as libc::socklen_t;
+        let control_size = if timestamps {
+            timestamp::CONTROL_SIZE
+        } else {
+            0
+        };
+        msghdr.msg_controllen = control_size as _;
+        let buffer_size = RECVMSG_OUT_SIZE + NAME_SIZE + control_size + SHRED_SIZE;
+
+        let mut batch = Self {
+            ring,
+            buffers: vec![0u8; buffer_size * num_buffers as usize],
+            buffer_size,
+            num_buffers,
+            msghdr,
+            packets: Vec::with_capacity(num_buffers as usize),
+            peer_addrs: Vec::with_capacity(num_buffers as usize),
+            received_at: Vec::with_capacity(num_buffers as usize),
+            used_buffers: Vec::with_capacity(num_buffers as usize),
+            cqes: Vec::with_capacity(num_buffers as usize * 2),
+            armed: false,
+        };
+
+        // Hand the whole pool to the kernel and wait for it to be accepted
+        let provide = opcode::ProvideBuffers::new(
+            batch.buffers.as_mut_ptr(),
+            buffer_size as i32,
+            num_buffers,
+            BUFFER_GROUP,
+            0,
+        )
+        .build()
+        .user_data(USER_DATA_PROVIDE);
+        batch.push(&provide)?;
+        batch.ring.submit_and_wait(1)?;
+        let result = batch
+            .ring
+            .completion()
+            .next()
+            .map(|cqe| cqe.result())
+            .unwrap_or(-libc::EIO);
+        if result < 0 {
+            return Err(std::io::Error::from_raw_os_error(-result));
+        }
+
+        batch.arm(socket)?;
+
+        Ok(batch)
+    }
+
+    fn push(&mut self, entry: &squeue::Entry) -> std::io::Result<()> {
+        // SAFETY: every entry references memory owned by self, which outlives the ring
+        unsafe {
+            if self.ring.submission().push(entry).is_err() {
+                self.ring.submit()?;
+                self.ring
+                    .submission()
+                    .push(entry)
+                    .map_err(|_| std::io::Error::other("io_uring submission queue full"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn arm(&mut self, socket: &Socket) -> std::io::Result<()> {
+        let recv = opcode::RecvMsgMulti::new(
+            types::Fd(socket.as_raw_fd()),
+            &*self.msghdr as *const libc::msghdr,
+            BUFFER_GROUP,
+        )
+        .build()
+        .user_data(USER_DATA_RECV);
+        self.push(&recv)?;
+        self.armed = true;
+
+        Ok(())
+    }
+
+    fn provide(&mut self, buffer_id: u16) -> std::io::Result<()> {
+        let offset = buffer_id as usize * self.buffer_size;
+        let provide = opcode::ProvideBuffers::new(
+            // SAFETY: offset is within `buffers` since buffer_id < num_buffers
+            unsafe { self.buffers.as_mut_ptr().add(offset) },
+            self.buffer_size as i32,
+            1,
+            BUFFER_GROUP,
+            buffer_id,
+        )
+        .build()
+        .user_data(USER_DATA_PROVIDE);
+        self.push(&provide)
+    }
+}
+
+impl PacketBatch for UringBatch {
+    fn recv(&mut self, socket: &Socket) -> std::io::Result<usize> {
+        // Packets of the last batch have been consumed, return their buffers
+        for i in 0..self.used_buffers.len() {
+            self.provide(self.used_buffers[i])?;
+        }
+        self.used_buffers.clear();
+        self.packets.clear();
+        self.peer_addrs.clear();
+        self.received_at.clear();
+
+        if !self.armed {
+            self.arm(socket)?;
+        }
+
+        match self.ring.submit_and_wait(1) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => return Ok(0),
+            Err(e) => return Err(e),
+        }
+
+        self.cqes.clear();
+        self.cqes.extend(
+            self.ring
+                .completion()
+                .map(|cqe| (cqe.user_data(), cqe.result(), cqe.flags())),
+        );
+
+        for i in 0..self.cqes.len() {
+            let (user_data, result, flags) = self.cqes[i];
+            if user_data == USER_DATA_PROVIDE {
+                if result < 0 {
+                    error!(
+                        "io_uring failed to provide buffers: {}",
+                        std::io::Error::from_raw_os_error(-result)
+                    );
+                }
+                continue;
+            }
+
+            if !cqueue::more(flags) {
+                // Multishot terminated (e.g. buffer pool ran dry), re-arm on next call
+                self.armed = false;
+            }
+
+            if result < 0 {
+                // ENOBUFS is expected under bursts, buffers are returned on the next call
+                if result != -libc::ENOBUFS {
+                    return Err(std::io::Error::from_raw_os_error(-result));
+                }
+                continue;
+            }
+
+            let Some(buffer_id) = cqueue::buffer_select(flags) else {
+                continue;
+            };
+            if buffer_id >= self.num_buffers {
+                continue;
+            }
+            self.used_buffers.push(buffer_id);
+
+            let start = buffer_id as usize * self.buffer_size;
+            let end = start + std::cmp::min(result as usize, self.buffer_size);
+            if let Ok(out) = types::RecvMsgOut::parse(&self.buffers[start..end], &self.msghdr) {
+                let payload = out.payload_data();
+                let offset = payload.as_ptr() as usize - self.buffers.as_ptr() as usize;
+                self.packets.push((offset, payload.len()));
+
+                let name = out.name_data();
+                // SAFETY: sockaddr_storage is a plain C struct, all-zero is valid
+                let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+                let len = std::cmp::min(name.len(), NAME_SIZE);
+                // SAFETY: copies at most size_of::<sockaddr_storage>() bytes
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        name.as_ptr(),
+                        &mut storage as *mut _ as *mut u8,
+                        len,
+                    );
+                }
+                self.peer_addrs
+                    .push(socket_addr_from_raw(&storage, len as libc::socklen_t));
+                self.received_at.push(timestamp::parse(out.control_data()));
+            }
+        }
+
+        Ok(self.packets.len())
+    }
+
+    fn packet(&self, i: usize) -> &[u8] {
+        let (offset, len) = self.packets[i];
+        &self.buffers[offset..offset + len]
+    }
+
+    fn peer_addr(&self, i: usize) -> Option<SocketAddr> {
+        self.peer_addrs[i]
+    }
+
+    fn received_at(&self, i: usize) -> Option<(u64, Clock)> {
+        self.received_at[i]
+    }
+}
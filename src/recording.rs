@@ -0,0 +1,712 @@
+//! Append-only shred recording files.
+//!
+//! A file starts with `MAGIC` and a u16 format version, followed by records
+//! (little endian):
+//! * `RECORD_FEED`  - u16 feed id, u8 name length, name
+//! * `RECORD_SHRED` - u16 feed id, u64 received_at_micros (`u64::MAX` if unknown),
+//!   u8 address family (0 none, 4, 6), IP + u16 port if known, u16 length, payload
+//!
+//! Feed ids are declared before their first use in each file. Files written
+//! with `zstd_level` are a single zstd stream over the same layout.
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{
+    config::RecordingConfig,
+    forwarder::Forwarder,
+    types::{ShredBytesMeta, ShredOrigin},
+};
+
+use ahash::{HashMap, HashMapExt};
+use anyhow::Result;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::{error::TryRecvError, Receiver};
+use tracing::{error, info, warn};
+
+pub const MAGIC: &[u8; 8] = b"UNSHRED\0";
+pub const VERSION: u16 = 1;
+pub const RECORD_FEED: u8 = 0;
+pub const RECORD_SHRED: u8 = 1;
+pub const NO_TIMESTAMP: u64 = u64::MAX;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+const FILE_EXTENSION: &str = "shreds";
+const DEFAULT_PREFIX: &str = "shreds";
+const DEFAULT_MAX_FILE_BYTES: u64 = 1024 * 1024 * 1024; // 1GB
+                                                        // Written shreds reach the file at least this often, also once the feeds go quiet
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// Uncompressed bytes written before flushing ahead of `FLUSH_INTERVAL`
+const FLUSH_BYTES: u64 = 8 * 1024 * 1024;
+// Queue polling period while written shreds wait for their flush
+const FLUSH_POLL: Duration = Duration::from_millis(10);
+
+/// Hands received datagrams to the recording writer thread
+#[derive(Clone)]
+pub struct Recorder {
+    forwarder: Forwarder<ShredBytesMeta>,
+}
+
+impl Recorder {
+    /// Spawns the writer thread, which stops once every `Recorder` clone is dropped
+    pub fn start(config: &RecordingConfig) -> Result<Self> {
+        #[cfg(not(feature = "zstd"))]
+        if config.zstd_level.is_some() {
+            return Err(anyhow::anyhow!(
+                "Compressed recordings require `features = [\"zstd\"]`"
+            ));
+        }
+
+        std::fs::create_dir_all(&config.directory)?;
+        let writer = RecordingWriter::new(config.clone());
+        let forwarder = Forwarder::spawn("unshred-recorder", move |receiver| writer.run(receiver))?;
+        info!("Recording shreds to {}", config.directory);
+
+        Ok(Self { forwarder })
+    }
+
+    pub fn record(&self, buffer: &[u8], received_at_micros: Option<u64>, origin: &ShredOrigin) {
+        let shred = ShredBytesMeta {
+            shred_bytes: Arc::new(buffer.to_vec()),
+            received_at_micros,
+            origin: origin.clone(),
+        };
+
+        if !self.forwarder.try_forward(shred) {
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = Metrics::try_get() {
+                metrics
+                    .recorder_shreds
+                    .with_label_values(&["dropped"])
+                    .inc();
+            }
+        }
+    }
+}
+
+enum Output {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, BufWriter<File>>),
+}
+
+impl Output {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Plain(writer) => writer,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder,
+        }
+    }
+
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(mut writer) => writer.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+/// Recording file currently written to
+struct RecordingFile {
+    output: Output,
+    path: PathBuf,
+    written: u64, // Uncompressed bytes
+    unflushed: u64,
+    flushed_at: Instant,
+    feeds: HashMap<Arc<str>, u16>,
+}
+
+struct RecordingWriter {
+    config: RecordingConfig,
+    file: Option<RecordingFile>,
+    record: Vec<u8>,
+}
+
+impl RecordingWriter {
+    fn new(config: RecordingConfig) -> Self {
+        Self {
+            config,
+            file: None,
+            record: Vec::with_capacity(2048),
+        }
+    }
+
+    fn run(mut self, mut receiver: Receiver<ShredBytesMeta>) {
+        loop {
+            let unflushed = self.file.as_ref().is_some_and(|file| file.unflushed > 0);
+            let shred = if unflushed {
+                // Polled, so the last shreds before the feeds go quiet get flushed too
+                match receiver.try_recv() {
+                    Ok(shred) => shred,
+                    Err(TryRecvError::Empty) => {
+                        if !self.flush_if_due() {
+                            std::thread::sleep(FLUSH_POLL);
+                        }
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            } else {
+                match receiver.blocking_recv() {
+                    Some(shred) => shred,
+                    None => break,
+                }
+            };
+
+            self.write_or_log(&shred);
+            self.flush_if_due();
+        }
+
+        if let Some(file) = self.file.take() {
+            Self::finish(file);
+        }
+    }
+
+    /// Flushes the current file after `FLUSH_BYTES` or `FLUSH_INTERVAL`, whether it did
+    fn flush_if_due(&mut self) -> bool {
+        let Some(file) = self.file.as_mut() else {
+            return false;
+        };
+        if file.unflushed < FLUSH_BYTES && file.flushed_at.elapsed() < FLUSH_INTERVAL {
+            return false;
+        }
+
+        if let Err(e) = file.output.writer().flush() {
+            error!("Failed to flush recording {}: {}", file.path.display(), e);
+        }
+        file.unflushed = 0;
+        file.flushed_at = Instant::now();
+
+        true
+    }
+
+    /// Completes the file, including the end of its zstd stream
+    fn finish(file: RecordingFile) {
+        match file.output.finish() {
+            Ok(()) => info!("Finished recording {}", file.path.display()),
+            Err(e) => error!("Failed to finish recording {}: {}", file.path.display(), e),
+        }
+    }
+
+    fn write_or_log(&mut self, shred: &ShredBytesMeta) {
+        let result = self.write(shred);
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .recorder_shreds
+                .with_label_values(&[if result.is_ok() { "written" } else { "failed" }])
+                .inc();
+        }
+
+        if let Err(e) = result {
+            error!("Failed to record shred: {}", e);
+            // Start over with a fresh file
+            if let Some(file) = self.file.take() {
+                Self::finish(file);
+            }
+        }
+    }
+
+    fn write(&mut self, shred: &ShredBytesMeta) -> Result<()> {
+        let max_file_bytes = self.config.max_file_bytes.unwrap_or(DEFAULT_MAX_FILE_BYTES);
+        if self
+            .file
+            .as_ref()
+            .is_none_or(|file| file.written >= max_file_bytes)
+        {
+            self.rotate()?;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+
+        self.record.clear();
+        let feed_id = match file.feeds.get(&shred.origin.feed) {
+            Some(id) => *id,
+            None => {
+                let id = file.feeds.len() as u16;
+                let name = &shred.origin.feed.as_bytes()[..shred.origin.feed.len().min(255)];
+                self.record.push(RECORD_FEED);
+                self.record.extend_from_slice(&id.to_le_bytes());
+                self.record.push(name.len() as u8);
+                self.record.extend_from_slice(name);
+                file.feeds.insert(Arc::clone(&shred.origin.feed), id);
+                id
+            }
+        };
+
+        self.record.push(RECORD_SHRED);
+        self.record.extend_from_slice(&feed_id.to_le_bytes());
+        self.record.extend_from_slice(
+            &shred
+                .received_at_micros
+                .unwrap_or(NO_TIMESTAMP)
+                .to_le_bytes(),
+        );
+        match shred.origin.peer_addr {
+            None => self.record.push(0),
+            Some(addr) => {
+                match addr.ip() {
+                    IpAddr::V4(ip) => {
+                        self.record.push(4);
+                        self.record.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        self.record.push(6);
+                        self.record.extend_from_slice(&ip.octets());
+                    }
+                }
+                self.record.extend_from_slice(&addr.port().to_le_bytes());
+            }
+        }
+        let payload = &shred.shred_bytes[..shred.shred_bytes.len().min(u16::MAX as usize)];
+        self.record
+            .extend_from_slice(&(payload.len() as u16).to_le_bytes());
+        self.record.extend_from_slice(payload);
+
+        file.output.writer().write_all(&self.record)?;
+        file.written += self.record.len() as u64;
+        file.unflushed += self.record.len() as u64;
+
+        Ok(())
+    }
+
+    /// Finishes the current file, opens the next one and enforces `max_files`
+    fn rotate(&mut self) -> Result<()> {
+        if let Some(file) = self.file.take() {
+            Self::finish(file);
+        }
+
+        let prefix = self.config.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+        let started_at_micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        // Zero padded so names sort by start time
+        let mut name = format!("{}-{:020}.{}", prefix, started_at_micros, FILE_EXTENSION);
+        if self.config.zstd_level.is_some() {
+            name.push_str(".zst");
+        }
+        let path = Path::new(&self.config.directory).join(name);
+
+        let writer = BufWriter::new(File::create_new(&path)?);
+        let mut output = match self.config.zstd_level {
+            None => Output::Plain(writer),
+            #[cfg(feature = "zstd")]
+            Some(level) => Output::Zstd(zstd::stream::write::Encoder::new(writer, level)?),
+            #[cfg(not(feature = "zstd"))]
+            Some(_) => Output::Plain(writer),
+        };
+        output.writer().write_all(MAGIC)?;
+        output.writer().write_all(&VERSION.to_le_bytes())?;
+
+        self.file = Some(RecordingFile {
+            output,
+            path,
+            written: (MAGIC.len() + 2) as u64,
+            unflushed: (MAGIC.len() + 2) as u64,
+            flushed_at: Instant::now(),
+            feeds: HashMap::new(),
+        });
+
+        if let Some(max_files) = self.config.max_files {
+            if let Err(e) = self.remove_old_files(prefix, max_files) {
+                error!("Failed to remove old recordings: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove_old_files(&self, prefix: &str, max_files: usize) -> Result<()> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.config.directory)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_recording_file(path, Some(prefix)))
+            .collect();
+        files.sort();
+
+        let excess = files.len().saturating_sub(std::cmp::max(max_files, 1));
+        for path in &files[..excess] {
+            std::fs::remove_file(path)?;
+            info!("Removed old recording {}", path.display());
+        }
+
+        Ok(())
+    }
+}
+
+/// A shred record read back from a recording file
+pub struct RecordedShred<'a> {
+    pub bytes: &'a [u8],
+    pub received_at_micros: Option<u64>,
+    pub feed: &'a str,
+    pub peer_addr: Option<SocketAddr>,
+}
+
+struct ShredRecordHeader {
+    feed_id: u16,
+    received_at_micros: Option<u64>,
+    peer_addr: Option<SocketAddr>,
+}
+
+/// Sequential reader of one recording file, plain or zstd-compressed
+pub struct RecordingReader {
+    reader: Box<dyn Read + Send>,
+    path: PathBuf,
+    feeds: HashMap<u16, String>,
+    payload: Vec<u8>,
+    current: Option<ShredRecordHeader>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let compressed = file.fill_buf()?.starts_with(&ZSTD_MAGIC);
+        let mut reader: Box<dyn Read + Send> = match compressed {
+            false => Box::new(file),
+            #[cfg(feature = "zstd")]
+            true => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+            #[cfg(not(feature = "zstd"))]
+            true => {
+                return Err(anyhow::anyhow!(
+                    "Compressed recording {} requires `features = [\"zstd\"]`",
+                    path.display()
+                ))
+            }
+        };
+
+        let mut header = [0u8; MAGIC.len() + 2];
+        reader.read_exact(&mut header)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(anyhow::anyhow!("{} is not a recording", path.display()));
+        }
+        let version = u16::from_le_bytes([header[MAGIC.len()], header[MAGIC.len() + 1]]);
+        if version != VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported recording version {} in {}",
+                version,
+                path.display()
+            ));
+        }
+
+        Ok(Self {
+            reader,
+            path: path.to_path_buf(),
+            feeds: HashMap::new(),
+            payload: Vec::with_capacity(2048),
+            current: None,
+        })
+    }
+
+    /// Moves to the next shred record, `false` at the end of the file.
+    /// A record cut off by a crash of the writer also ends the file.
+    pub fn advance(&mut self) -> Result<bool> {
+        self.current = None;
+
+        loop {
+            let mut kind = [0u8; 1];
+            if self.reader.read(&mut kind)? == 0 {
+                return Ok(false);
+            }
+
+            match self.read_record(kind[0]) {
+                Ok(Some(header)) => {
+                    if !self.feeds.contains_key(&header.feed_id) {
+                        return Err(anyhow::anyhow!(
+                            "Undeclared feed {} in {}",
+                            header.feed_id,
+                            self.path.display()
+                        ));
+                    }
+                    self.current = Some(header);
+                    return Ok(true);
+                }
+                Ok(None) => continue,
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    warn!(
+                        "Recording {} ends with a partial record",
+                        self.path.display()
+                    );
+                    return Ok(false);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Shred record `advance` moved to
+    pub fn current(&self) -> Option<RecordedShred<'_>> {
+        let header = self.current.as_ref()?;
+
+        Some(RecordedShred {
+            bytes: &self.payload,
+            received_at_micros: header.received_at_micros,
+            feed: self.feeds.get(&header.feed_id)?,
+            peer_addr: header.peer_addr,
+        })
+    }
+
+    /// Reads the record body, returning the header of `RECORD_SHRED` records
+    fn read_record(&mut self, kind: u8) -> std::io::Result<Option<ShredRecordHeader>> {
+        let feed_id = self.read_u16()?;
+
+        match kind {
+            RECORD_FEED => {
+                let mut len = [0u8; 1];
+                self.reader.read_exact(&mut len)?;
+                let mut name = vec![0u8; len[0] as usize];
+                self.reader.read_exact(&mut name)?;
+                self.feeds
+                    .insert(feed_id, String::from_utf8_lossy(&name).into_owned());
+                Ok(None)
+            }
+            RECORD_SHRED => {
+                let mut received_at = [0u8; 8];
+                self.reader.read_exact(&mut received_at)?;
+                let received_at = u64::from_le_bytes(received_at);
+
+                let mut family = [0u8; 1];
+                self.reader.read_exact(&mut family)?;
+                let ip = match family[0] {
+                    0 => None,
+                    4 => {
+                        let mut octets = [0u8; 4];
+                        self.reader.read_exact(&mut octets)?;
+                        Some(IpAddr::from(octets))
+                    }
+                    6 => {
+                        let mut octets = [0u8; 16];
+                        self.reader.read_exact(&mut octets)?;
+                        Some(IpAddr::from(octets))
+                    }
+                    family => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("Unknown address family {}", family),
+                        ))
+                    }
+                };
+                let peer_addr = match ip {
+                    Some(ip) => Some(SocketAddr::new(ip, self.read_u16()?)),
+                    None => None,
+                };
+
+                let len = self.read_u16()? as usize;
+                self.payload.resize(len, 0);
+                self.reader.read_exact(&mut self.payload)?;
+
+                Ok(Some(ShredRecordHeader {
+                    feed_id,
+                    received_at_micros: (received_at != NO_TIMESTAMP).then_some(received_at),
+                    peer_addr,
+                }))
+            }
+            kind => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown record kind {}", kind),
+            )),
+        }
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+}
+
+/// Recording files in `directory`, oldest first
+pub fn recording_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_recording_file(path, None))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// `<prefix>-<start>.shreds[.zst]`, any prefix if `None`
+fn is_recording_file(path: &Path, prefix: Option<&str>) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = name.strip_suffix(".zst").unwrap_or(name);
+
+    name.strip_suffix(&format!(".{}", FILE_EXTENSION))
+        .and_then(|stem| stem.rsplit_once('-'))
+        .is_some_and(|(stem_prefix, _)| prefix.is_none_or(|prefix| prefix == stem_prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Recorded = (Vec<u8>, Option<u64>, String, Option<SocketAddr>);
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("unshred-recording-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn shred(
+        bytes: &[u8],
+        received_at_micros: Option<u64>,
+        feed: &str,
+        peer_addr: Option<&str>,
+    ) -> ShredBytesMeta {
+        ShredBytesMeta {
+            shred_bytes: Arc::new(bytes.to_vec()),
+            received_at_micros,
+            origin: ShredOrigin {
+                feed: Arc::from(feed),
+                peer_addr: peer_addr.map(|addr| addr.parse().unwrap()),
+            },
+        }
+    }
+
+    /// Records `shreds` through the writer thread's loop, returning the single file written
+    fn record(dir: &Path, zstd_level: Option<i32>, shreds: Vec<ShredBytesMeta>) -> PathBuf {
+        let writer = RecordingWriter::new(RecordingConfig {
+            directory: dir.to_string_lossy().into_owned(),
+            prefix: None,
+            max_file_bytes: None,
+            max_files: None,
+            zstd_level,
+        });
+        let (sender, receiver) = tokio::sync::mpsc::channel(shreds.len());
+        for shred in shreds {
+            sender.try_send(shred).unwrap();
+        }
+        drop(sender);
+        writer.run(receiver);
+
+        let mut files = recording_files(dir).unwrap();
+        assert_eq!(files.len(), 1);
+        files.pop().unwrap()
+    }
+
+    fn read(path: &Path) -> Vec<Recorded> {
+        let mut reader = RecordingReader::open(path).unwrap();
+        let mut shreds = Vec::new();
+        while reader.advance().unwrap() {
+            let shred = reader.current().unwrap();
+            shreds.push((
+                shred.bytes.to_vec(),
+                shred.received_at_micros,
+                shred.feed.to_string(),
+                shred.peer_addr,
+            ));
+        }
+        shreds
+    }
+
+    fn shreds() -> Vec<ShredBytesMeta> {
+        vec![
+            shred(b"first", Some(1_000), "turbine", Some("10.0.0.1:8001")),
+            shred(b"second", None, "jito", Some("[2001:db8::1]:9000")),
+            shred(b"third", Some(3_000), "turbine", None),
+        ]
+    }
+
+    fn expected() -> Vec<Recorded> {
+        vec![
+            (
+                b"first".to_vec(),
+                Some(1_000),
+                "turbine".to_string(),
+                Some("10.0.0.1:8001".parse().unwrap()),
+            ),
+            (
+                b"second".to_vec(),
+                None,
+                "jito".to_string(),
+                Some("[2001:db8::1]:9000".parse().unwrap()),
+            ),
+            (b"third".to_vec(), Some(3_000), "turbine".to_string(), None),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir("round-trip");
+        let path = record(&dir, None, shreds());
+
+        assert_eq!(read(&path), expected());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn round_trip_zstd() {
+        let dir = temp_dir("round-trip-zstd");
+        let path = record(&dir, Some(3), shreds());
+
+        assert!(path.to_string_lossy().ends_with(".shreds.zst"));
+        assert_eq!(read(&path), expected());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_trailing_record() {
+        let dir = temp_dir("truncated");
+        let path = record(&dir, None, shreds());
+
+        // Cut into the payload of the last record, as a crash of the writer would
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 2).unwrap();
+
+        assert_eq!(read(&path), expected()[..2]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quiet_feeds_are_flushed() {
+        let dir = temp_dir("quiet");
+        let writer = RecordingWriter::new(RecordingConfig {
+            directory: dir.to_string_lossy().into_owned(),
+            prefix: None,
+            max_file_bytes: None,
+            max_files: None,
+            zstd_level: None,
+        });
+        let (sender, receiver) = tokio::sync::mpsc::channel(16);
+        let thread = std::thread::spawn(move || writer.run(receiver));
+        for shred in shreds() {
+            sender.try_send(shred).unwrap();
+        }
+
+        // Readable while the writer still waits for shreds
+        std::thread::sleep(FLUSH_INTERVAL + 20 * FLUSH_POLL);
+        let files = recording_files(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(read(&files[0]), expected());
+
+        drop(sender);
+        thread.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = temp_dir("other");
+        let path = dir.join("shreds-1.shreds");
+        std::fs::write(&path, b"not a recording").unwrap();
+
+        assert!(RecordingReader::open(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
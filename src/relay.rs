@@ -0,0 +1,142 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{config::RelayConfig, forwarder::Forwarder, header};
+
+use ahash::{HashMap, HashSet};
+use anyhow::Result;
+use std::net::{SocketAddr, UdpSocket};
+use tokio::sync::mpsc::Receiver;
+use tracing::{error, info};
+
+// Slots behind the highest seen slot whose dedup state is kept
+const DEDUP_SLOTS: u64 = 256;
+
+/// Hands shreds to the relay sender thread
+#[derive(Clone)]
+pub struct Relay {
+    forwarder: Forwarder<Vec<u8>>,
+}
+
+impl Relay {
+    /// Spawns the sender thread, which stops once every `Relay` clone is dropped
+    pub fn start(config: &RelayConfig) -> Result<Self> {
+        let destinations = config
+            .destinations
+            .iter()
+            .map(|destination| destination.parse())
+            .collect::<Result<Vec<SocketAddr>, _>>()?;
+        if destinations.is_empty() {
+            return Err(anyhow::anyhow!("Relay requires at least one destination"));
+        }
+        let sender_v4 = destinations
+            .iter()
+            .any(SocketAddr::is_ipv4)
+            .then(|| UdpSocket::bind("0.0.0.0:0"))
+            .transpose()?;
+        let sender_v6 = destinations
+            .iter()
+            .any(SocketAddr::is_ipv6)
+            .then(|| UdpSocket::bind("[::]:0"))
+            .transpose()?;
+
+        let relay = RelaySender {
+            destinations,
+            sender_v4,
+            sender_v6,
+            dedup: config.dedup.then(ShredDedup::default),
+        };
+        let forwarder = Forwarder::spawn("unshred-relay", move |receiver| relay.run(receiver))?;
+        info!("Relaying shreds to {:?}", config.destinations);
+
+        Ok(Self { forwarder })
+    }
+
+    pub fn forward(&self, buffer: &[u8]) {
+        if !self.forwarder.try_forward(buffer.to_vec()) {
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = Metrics::try_get() {
+                metrics.relay_shreds.with_label_values(&["dropped"]).inc();
+            }
+        }
+    }
+}
+
+/// Shreds already forwarded, for the most recent slots
+#[derive(Default)]
+struct ShredDedup {
+    slots: HashMap<u64, HashSet<(u32, bool)>>, // slot -> (index, is_data)
+    highest_slot: u64,
+}
+
+impl ShredDedup {
+    /// Returns false if the shred was already seen
+    fn insert(&mut self, buffer: &[u8]) -> bool {
+        let (Some(slot), Some(index), Some(is_data)) = (
+            header::slot(buffer),
+            header::index(buffer),
+            header::is_data(buffer),
+        ) else {
+            return true;
+        };
+
+        if slot > self.highest_slot {
+            self.highest_slot = slot;
+            let oldest_slot = slot.saturating_sub(DEDUP_SLOTS);
+            self.slots.retain(|slot, _| *slot >= oldest_slot);
+        }
+
+        self.slots.entry(slot).or_default().insert((index, is_data))
+    }
+}
+
+struct RelaySender {
+    destinations: Vec<SocketAddr>,
+    sender_v4: Option<UdpSocket>,
+    sender_v6: Option<UdpSocket>,
+    dedup: Option<ShredDedup>,
+}
+
+impl RelaySender {
+    fn run(mut self, mut receiver: Receiver<Vec<u8>>) {
+        while let Some(buffer) = receiver.blocking_recv() {
+            if buffer.len() < header::MIN_SHRED_LEN {
+                continue;
+            }
+
+            if let Some(dedup) = self.dedup.as_mut() {
+                if !dedup.insert(&buffer) {
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = Metrics::try_get() {
+                        metrics.relay_shreds.with_label_values(&["duplicate"]).inc();
+                    }
+                    continue;
+                }
+            }
+
+            for destination in &self.destinations {
+                let socket = match destination {
+                    SocketAddr::V4(_) => self.sender_v4.as_ref(),
+                    SocketAddr::V6(_) => self.sender_v6.as_ref(),
+                };
+                let Some(socket) = socket else {
+                    continue;
+                };
+                let result = socket.send_to(&buffer, destination);
+
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    let outcome = if result.is_ok() {
+                        "forwarded"
+                    } else {
+                        "failed"
+                    };
+                    metrics.relay_shreds.with_label_values(&[outcome]).inc();
+                }
+
+                if let Err(e) = result {
+                    error!("Failed to relay shred to {}: {}", destination, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    recording::{recording_files, RecordingReader},
+    source::{ShredSource, SourceShred},
+};
+
+use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Feeds shreds as fast as the pipeline takes them, for throughput testing
+    AsFastAsPossible,
+    /// Spaces shreds as originally received, for latency studies
+    Paced,
+}
+
+/// Spaces replayed shreds by their original arrival times
+#[derive(Debug, Default)]
+pub struct Pacer {
+    // (replay start, first received_at_micros)
+    origin: Option<(Instant, u64)>,
+}
+
+impl Pacer {
+    /// Sleeps until `received_at_micros` is due relative to the first paced shred
+    pub fn wait(&mut self, received_at_micros: u64) {
+        let (started_at, first_received_at) = *self
+            .origin
+            .get_or_insert((Instant::now(), received_at_micros));
+        let due = started_at
+            + Duration::from_micros(received_at_micros.saturating_sub(first_received_at));
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
+        }
+    }
+}
+
+/// Replays recording files through the pipeline.
+///
+/// Shreds keep their recorded `received_at_micros` and feed, so `TransactionEvent`s
+/// and race stats match the original capture. Never drops shreds on backpressure.
+pub struct ReplaySource {
+    name: String,
+    files: VecDeque<PathBuf>,
+    reader: Option<RecordingReader>,
+    mode: ReplayMode,
+    pacer: Pacer,
+}
+
+impl ReplaySource {
+    /// Replays `paths` in order, a directory expands to its recording files, oldest first
+    pub fn new(
+        paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+        mode: ReplayMode,
+    ) -> Result<Self> {
+        let mut files = VecDeque::new();
+        for path in paths {
+            let path = path.into();
+            if path.is_dir() {
+                files.extend(recording_files(&path)?);
+            } else {
+                files.push_back(path);
+            }
+        }
+
+        Ok(Self {
+            name: "replay".to_string(),
+            files,
+            reader: None,
+            mode,
+            pacer: Pacer::default(),
+        })
+    }
+
+    /// Name used for logs and for shreds recorded without a feed
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+impl ShredSource for ReplaySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_shred(&mut self) -> Result<Option<SourceShred<'_>>> {
+        // Move to the next shred record, opening files as they run out
+        loop {
+            match self.reader.as_mut() {
+                Some(reader) => {
+                    if reader.advance()? {
+                        break;
+                    }
+                    self.reader = None;
+                }
+                None => {
+                    let Some(path) = self.files.pop_front() else {
+                        return Ok(None);
+                    };
+                    info!("Replaying {}", path.display());
+                    self.reader = Some(RecordingReader::open(&path)?);
+                }
+            }
+        }
+        let Some(shred) = self.reader.as_ref().and_then(|reader| reader.current()) else {
+            return Ok(None);
+        };
+
+        if let (ReplayMode::Paced, Some(received_at)) = (self.mode, shred.received_at_micros) {
+            self.pacer.wait(received_at);
+        }
+
+        Ok(Some(SourceShred {
+            bytes: shred.bytes,
+            received_at_micros: shred.received_at_micros,
+            peer_addr: shred.peer_addr,
+            feed: Some(shred.feed),
+        }))
+    }
+
+    fn wait_for_capacity(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{
+    dedup::ProcessedFecSets,
+    filter::SourceFilter,
+    header::{self, SIGNATURE_LEN},
+    race::LateShreds,
+    recording::Recorder,
+    relay::Relay,
+    stage::{SendMode, StageSender},
+    tip::SlotTip,
+    types::{ShredBytesMeta, ShredOrigin},
+    version::ShredVersionCheck,
+};
+
+use ahash::{HashMap, HashMapExt};
+use anyhow::Result;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::task;
+use tracing::{error, info, trace};
+
+/// A shred pulled from a `ShredSource`
+#[derive(Debug)]
+pub struct SourceShred<'a> {
+    /// Raw shred payload, copied only if its FEC set is still being accumulated
+    pub bytes: &'a [u8],
+    /// Arrival time in micros since the Unix epoch, `None` if unknown
+    pub received_at_micros: Option<u64>,
+    /// Sender of the shred, if known
+    pub peer_addr: Option<SocketAddr>,
+    /// Feed of this shred, for sources multiplexing several feeds such as replays.
+    /// Defaults to the source's `name()`.
+    pub feed: Option<&'a str>,
+}
+
+/// Where the pipeline pulls shreds from, e.g. a socket, a file or a channel.
+///
+/// Each source is driven by its own blocking thread.
+pub trait ShredSource: Send + 'static {
+    /// Feed name used in logs, metric labels and race stats
+    fn name(&self) -> &str;
+
+    /// Blocks until the next shred is available
+    /// # Returns
+    /// * `Ok(Some(_))` - the next shred
+    /// * `Ok(None)`    - the source is exhausted. The pipeline drains and `run` returns
+    ///   once every source is exhausted.
+    /// * `Err(_)`      - to log error and stop this source
+    fn next_shred(&mut self) -> Result<Option<SourceShred<'_>>>;
+
+    /// Wait for room in full FEC worker queues instead of applying their `BackpressurePolicy`.
+    /// Live sources follow the policy to keep up, offline sources such as replays should wait.
+    fn wait_for_capacity(&self) -> bool {
+        false
+    }
+}
+
+/// Optional checks and taps the router applies to every shred, in field order
+#[derive(Clone, Default)]
+pub struct RouterHooks {
+    pub source_filter: Option<Arc<SourceFilter>>,
+    pub recorder: Option<Recorder>,
+    pub shred_version: Option<Arc<ShredVersionCheck>>,
+    pub relay: Option<Relay>,
+}
+
+/// Sends shreds to the FEC worker owning their FEC set, dropping shreds of completed sets
+#[derive(Clone)]
+pub struct ShredRouter {
+    senders: Vec<StageSender<ShredBytesMeta>>,
+    processed_fec_sets: Arc<ProcessedFecSets>,
+    late_shreds: Arc<LateShreds>,
+    slot_tip: Arc<SlotTip>,
+    hooks: RouterHooks,
+}
+
+impl ShredRouter {
+    pub fn new(
+        senders: Vec<StageSender<ShredBytesMeta>>,
+        processed_fec_sets: Arc<ProcessedFecSets>,
+        late_shreds: Arc<LateShreds>,
+        slot_tip: Arc<SlotTip>,
+        hooks: RouterHooks,
+    ) -> Self {
+        Self {
+            senders,
+            processed_fec_sets,
+            late_shreds,
+            slot_tip,
+            hooks,
+        }
+    }
+
+    /// Creates ShredBytesMeta and sends through `senders`
+    /// * `wait` - block while the FEC worker queue is full instead of applying its
+    ///   `BackpressurePolicy`
+    pub fn route(
+        &self,
+        buffer: &[u8],
+        received_at_micros: Option<u64>,
+        origin: ShredOrigin,
+        wait: bool,
+    ) -> Result<()> {
+        let source_filter = self.hooks.source_filter.as_deref().zip(origin.peer_addr);
+        if let Some((source_filter, peer_addr)) = source_filter {
+            if let Err(reason) = source_filter.check(peer_addr.ip()) {
+                trace!("Dropped shred from {}: {}", peer_addr, reason);
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .errors
+                        .with_label_values(&["receiver", reason])
+                        .inc();
+                }
+                return Ok(()); // Dropped
+            }
+        }
+
+        if let Some(recorder) = &self.hooks.recorder {
+            recorder.record(buffer, received_at_micros, &origin);
+        }
+
+        if buffer.len() < header::MIN_SHRED_LEN {
+            if let Some((source_filter, peer_addr)) = source_filter {
+                source_filter.report_parse_failure(peer_addr.ip());
+            }
+            return Err(anyhow::anyhow!("Invalid shred size"));
+        }
+
+        if let Some(shred_version) = &self.hooks.shred_version {
+            if !shred_version.accepts(buffer) {
+                return Ok(()); // Foreign cluster
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .receiver_shreds_received
+                .with_label_values(&["raw"])
+                .inc();
+        }
+
+        if let Some(relay) = &self.hooks.relay {
+            relay.forward(buffer);
+        }
+
+        // Parse shred header
+        let (Some(slot), Some(fec_set_index)) =
+            (header::slot(buffer), header::fec_set_index(buffer))
+        else {
+            return Err(anyhow::anyhow!("Invalid shred size"));
+        };
+
+        if self.slot_tip.shed(slot, "receiver") {
+            return Ok(());
+        }
+
+        // Differently signed shreds of completed sets go on to the FEC worker for equivocation checks
+        let fec_key = (slot, fec_set_index);
+        if self
+            .processed_fec_sets
+            .contains_signed(&fec_key, &buffer[..SIGNATURE_LEN])
+        {
+            self.late_shreds.record(slot, &origin.feed);
+            return Ok(()); // Exit early
+        }
+
+        // Send ShredBytesMeta to processor
+        let worker_id = (fec_set_index as usize) % self.senders.len();
+        let sender = &self.senders[worker_id];
+        let shred_bytes_meta = ShredBytesMeta {
+            shred_bytes: Arc::new(buffer.to_vec()),
+            received_at_micros,
+            origin,
+        };
+        let mode = if wait {
+            SendMode::Wait
+        } else {
+            SendMode::Policy
+        };
+        sender.blocking_send(shred_bytes_meta, mode)
+    }
+}
+
+/// Pulls shreds from `source` on a blocking thread until it is exhausted
+pub async fn run_source(mut source: Box<dyn ShredSource>, router: ShredRouter) -> Result<()> {
+    let handle = task::spawn_blocking(move || {
+        let feed: Arc<str> = Arc::from(source.name());
+        let wait = source.wait_for_capacity();
+        // Interned feeds of multiplexing sources
+        let mut feeds: HashMap<String, Arc<str>> = HashMap::new();
+
+        loop {
+            let shred = match source.next_shred() {
+                Ok(Some(shred)) => shred,
+                Ok(None) => {
+                    info!("Shred source {} exhausted", feed);
+                    break;
+                }
+                Err(e) => {
+                    error!("Shred source {} failed: {}", feed, e);
+                    break;
+                }
+            };
+
+            let shred_feed = match shred.feed {
+                Some(name) if name != &*feed => match feeds.get(name) {
+                    Some(shred_feed) => Arc::clone(shred_feed),
+                    None => {
+                        let shred_feed: Arc<str> = Arc::from(name);
+                        feeds.insert(name.to_string(), Arc::clone(&shred_feed));
+                        shred_feed
+                    }
+                },
+                _ => Arc::clone(&feed),
+            };
+            let origin = ShredOrigin {
+                feed: shred_feed,
+                peer_addr: shred.peer_addr,
+            };
+            if let Err(e) = router.route(shred.bytes, shred.received_at_micros, origin, wait) {
+                error!("Source {} failed to process shred: {}", feed, e);
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = Metrics::try_get() {
+                    metrics
+                        .errors
+                        .with_label_values(&["receiver", "process_shred"])
+                        .inc();
+                }
+            }
+        }
+    });
+    handle.await?;
+
+    Ok(())
+}
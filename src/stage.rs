@@ -0,0 +1,689 @@
+use crate::config::{BackpressurePolicy, StageConfig};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use ahash::HashMap;
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::Future,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, Notify};
+use tracing::trace;
+
+/// Work items queued between pipeline stages, dropped by slot under `DropOldestSlot`
+pub trait SlotItem {
+    fn slot(&self) -> u64;
+}
+
+/// Whether a blocking send may wait for room
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendMode {
+    /// Apply the stage's `BackpressurePolicy`
+    Policy,
+    /// Wait as long as it takes, for offline sources
+    Wait,
+}
+
+struct Queued<T> {
+    seq: u64,
+    item: T,
+}
+
+struct State<T> {
+    // Also holds items of dropped slots, skipped once they reach the front
+    items: VecDeque<Queued<T>>,
+    // Queued items not dropped
+    len: usize,
+    // Queued items per slot
+    slots: BTreeMap<u64, usize>,
+    // Dropped slot -> (sequence number its dropped items are below, dropped items still queued)
+    dropped: HashMap<u64, (u64, usize)>,
+    next_seq: u64,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+impl<T: SlotItem> State<T> {
+    fn push_back(&mut self, item: T) {
+        *self.slots.entry(item.slot()).or_default() += 1;
+        self.items.push_back(Queued {
+            seq: self.next_seq,
+            item,
+        });
+        self.next_seq += 1;
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        while let Some(queued) = self.items.pop_front() {
+            let slot = queued.item.slot();
+            if let Some((below, remaining)) = self.dropped.get_mut(&slot) {
+                if queued.seq < *below {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        self.dropped.remove(&slot);
+                    }
+                    continue;
+                }
+            }
+
+            self.len -= 1;
+            if let Some(count) = self.slots.get_mut(&slot) {
+                *count -= 1;
+                if *count == 0 {
+                    self.slots.remove(&slot);
+                }
+            }
+            return Some(queued.item);
+        }
+
+        None
+    }
+
+    /// Drops every queued item of `slot`, the items are freed as they reach the front
+    /// or when dropped items make up more than half of the queue
+    fn drop_slot(&mut self, slot: u64) -> usize {
+        let Some(count) = self.slots.remove(&slot) else {
+            return 0;
+        };
+        let (below, remaining) = self.dropped.entry(slot).or_insert((0, 0));
+        *below = self.next_seq;
+        *remaining += count;
+        self.len -= count;
+
+        if self.items.len() > 2 * self.len {
+            let dropped = std::mem::take(&mut self.dropped);
+            self.items.retain(|queued| {
+                dropped
+                    .get(&queued.item.slot())
+                    .is_none_or(|(below, _)| queued.seq >= *below)
+            });
+        }
+
+        count
+    }
+}
+
+impl<T> State<T> {
+    fn clear(&mut self) {
+        self.items.clear();
+        self.len = 0;
+        self.slots.clear();
+        self.dropped.clear();
+    }
+}
+
+/// Queue of the `DropOldestSlot` policy, which needs to reach into the queued items.
+/// The other policies use a lock-free `mpsc` channel.
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    capacity: usize,
+    // Wakes the receiver
+    item_ready: Notify,
+    // Wakes blocking senders waiting for room under `SendMode::Wait`
+    space_ready: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Bounded queue between two pipeline stages, applying a `BackpressurePolicy` when full
+pub fn channel<T: SlotItem>(
+    stage: &'static str,
+    config: &StageConfig,
+    default_capacity: usize,
+    default_policy: BackpressurePolicy,
+) -> (StageSender<T>, StageReceiver<T>) {
+    let capacity = std::cmp::max(config.capacity.unwrap_or(default_capacity), 1);
+    let policy = config.policy.unwrap_or(default_policy);
+    let (sender, receiver) = match policy {
+        BackpressurePolicy::DropOldestSlot => {
+            let shared = Arc::new(Shared {
+                state: Mutex::new(State {
+                    items: VecDeque::with_capacity(capacity),
+                    len: 0,
+                    slots: BTreeMap::new(),
+                    dropped: HashMap::default(),
+                    next_seq: 0,
+                    senders: 1,
+                    receiver_alive: true,
+                }),
+                capacity,
+                item_ready: Notify::new(),
+                space_ready: Condvar::new(),
+            });
+            (
+                SenderQueue::Slots(SlotQueueSender {
+                    shared: Arc::clone(&shared),
+                }),
+                ReceiverQueue::Slots(SlotQueueReceiver { shared }),
+            )
+        }
+        BackpressurePolicy::DropNewest | BackpressurePolicy::Block { .. } => {
+            let (sender, receiver) = mpsc::channel(capacity);
+            (
+                SenderQueue::Channel(sender),
+                ReceiverQueue::Channel(receiver),
+            )
+        }
+    };
+
+    (
+        StageSender {
+            stage,
+            policy,
+            queue: sender,
+        },
+        StageReceiver {
+            #[cfg(feature = "metrics")]
+            capacity,
+            queue: receiver,
+        },
+    )
+}
+
+enum SenderQueue<T> {
+    Channel(mpsc::Sender<T>),
+    Slots(SlotQueueSender<T>),
+}
+
+pub struct StageSender<T> {
+    stage: &'static str,
+    policy: BackpressurePolicy,
+    queue: SenderQueue<T>,
+}
+
+impl<T: SlotItem> StageSender<T> {
+    /// Queues `item`, waiting for room asynchronously under `Block`
+    /// # Returns
+    /// * `Ok(())` - queued, or dropped and counted per the policy
+    /// * `Err(_)` - the receiving stage is gone
+    pub async fn send(&self, item: T) -> Result<()> {
+        let sender = match &self.queue {
+            SenderQueue::Channel(sender) => sender,
+            SenderQueue::Slots(queue) => return self.push_or_drop_oldest_slot(queue, item),
+        };
+        let BackpressurePolicy::Block { timeout_ms } = self.policy else {
+            return self.try_send(sender, item);
+        };
+
+        let sent = match timeout_ms {
+            Some(timeout_ms) => {
+                match tokio::time::timeout(Duration::from_millis(timeout_ms), sender.send(item))
+                    .await
+                {
+                    Ok(sent) => sent,
+                    Err(_) => {
+                        self.count_dropped("timeout", 1);
+                        return Ok(());
+                    }
+                }
+            }
+            None => sender.send(item).await,
+        };
+        sent.map_err(|_| anyhow::anyhow!("Channel disconnected"))
+    }
+
+    /// Like `send`, for blocking threads
+    pub fn blocking_send(&self, item: T, mode: SendMode) -> Result<()> {
+        let deadline = match (mode, self.policy) {
+            (SendMode::Wait, _) => Some(None),
+            (SendMode::Policy, BackpressurePolicy::Block { timeout_ms }) => Some(
+                timeout_ms.map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms)),
+            ),
+            (SendMode::Policy, _) => None,
+        };
+
+        match &self.queue {
+            SenderQueue::Channel(sender) => {
+                let Some(deadline) = deadline else {
+                    return self.try_send(sender, item);
+                };
+                match block_on(sender.send(item), deadline) {
+                    Some(sent) => sent.map_err(|_| anyhow::anyhow!("Channel disconnected")),
+                    None => {
+                        self.count_dropped("timeout", 1);
+                        Ok(())
+                    }
+                }
+            }
+            SenderQueue::Slots(queue) => match deadline {
+                // Only `SendMode::Wait` waits under `DropOldestSlot`
+                Some(_) => queue.wait_and_push(item),
+                None => self.push_or_drop_oldest_slot(queue, item),
+            },
+        }
+    }
+
+    /// Queues `item` without waiting, dropping it when full
+    fn try_send(&self, sender: &mpsc::Sender<T>, item: T) -> Result<()> {
+        match sender.try_send(item) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.count_dropped("drop_newest", 1);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                Err(anyhow::anyhow!("Channel disconnected"))
+            }
+        }
+    }
+
+    /// Queues `item` without waiting, dropping per `DropOldestSlot` when full
+    fn push_or_drop_oldest_slot(&self, queue: &SlotQueueSender<T>, item: T) -> Result<()> {
+        let mut state = queue.shared.lock();
+        if !state.receiver_alive {
+            return Err(anyhow::anyhow!("Channel disconnected"));
+        }
+
+        if state.len >= queue.shared.capacity {
+            match state.slots.first_key_value().map(|(slot, _)| *slot) {
+                // Make room by dropping everything queued for the oldest slot
+                Some(oldest_slot) if item.slot() > oldest_slot => {
+                    let dropped = state.drop_slot(oldest_slot);
+                    state.push_back(item);
+                    drop(state);
+                    queue.shared.item_ready.notify_one();
+                    self.count_dropped("drop_oldest_slot", dropped);
+                }
+                // The newest item belongs to the oldest slot itself
+                _ => {
+                    drop(state);
+                    self.count_dropped("drop_oldest_slot", 1);
+                }
+            }
+            return Ok(());
+        }
+
+        state.push_back(item);
+        drop(state);
+        queue.shared.item_ready.notify_one();
+
+        Ok(())
+    }
+
+    fn count_dropped(&self, reason: &str, count: usize) {
+        trace!("Stage {} dropped {} items: {}", self.stage, count, reason);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics
+                .stage_dropped
+                .with_label_values(&[self.stage, reason])
+                .inc_by(count as u64);
+        }
+    }
+}
+
+impl<T> Clone for StageSender<T> {
+    fn clone(&self) -> Self {
+        let queue = match &self.queue {
+            SenderQueue::Channel(sender) => SenderQueue::Channel(sender.clone()),
+            SenderQueue::Slots(queue) => SenderQueue::Slots(queue.clone()),
+        };
+        Self {
+            stage: self.stage,
+            policy: self.policy,
+            queue,
+        }
+    }
+}
+
+struct SlotQueueSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: SlotItem> SlotQueueSender<T> {
+    fn wait_and_push(&self, item: T) -> Result<()> {
+        let mut state = self.shared.lock();
+        loop {
+            if !state.receiver_alive {
+                return Err(anyhow::anyhow!("Channel disconnected"));
+            }
+            if state.len < self.shared.capacity {
+                state.push_back(item);
+                drop(state);
+                self.shared.item_ready.notify_one();
+                return Ok(());
+            }
+
+            state = self
+                .shared
+                .space_ready
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl<T> Clone for SlotQueueSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for SlotQueueSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            self.shared.item_ready.notify_one();
+        }
+    }
+}
+
+struct ThreadWaker(std::thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on the current thread until it completes or `deadline` passes.
+/// Unlike `mpsc::Sender::blocking_send`, it does not need to run outside of the runtime.
+fn block_on<F: Future>(future: F, deadline: Option<Instant>) -> Option<F::Output> {
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                std::thread::park_timeout(deadline - now);
+            }
+            None => std::thread::park(),
+        }
+    }
+}
+
+enum ReceiverQueue<T> {
+    Channel(mpsc::Receiver<T>),
+    Slots(SlotQueueReceiver<T>),
+}
+
+pub struct StageReceiver<T> {
+    #[cfg(feature = "metrics")]
+    capacity: usize,
+    queue: ReceiverQueue<T>,
+}
+
+impl<T: SlotItem> StageReceiver<T> {
+    /// Next queued item, `None` once the queue is empty and every sender is dropped
+    pub async fn recv(&mut self) -> Option<T> {
+        match &mut self.queue {
+            ReceiverQueue::Channel(receiver) => receiver.recv().await,
+            ReceiverQueue::Slots(queue) => queue.recv().await,
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn len(&self) -> usize {
+        match &self.queue {
+            ReceiverQueue::Channel(receiver) => receiver.len(),
+            ReceiverQueue::Slots(queue) => queue.shared.lock().len,
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+struct SlotQueueReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: SlotItem> SlotQueueReceiver<T> {
+    async fn recv(&mut self) -> Option<T> {
+        loop {
+            {
+                let mut state = self.shared.lock();
+                if let Some(item) = state.pop_front() {
+                    drop(state);
+                    self.shared.space_ready.notify_all();
+                    return Some(item);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+
+            // Holds a permit if an item was queued since the check
+            self.shared.item_ready.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for SlotQueueReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receiver_alive = false;
+        state.clear();
+        drop(state);
+        self.shared.space_ready.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (slot, index)
+    type Item = (u64, u32);
+
+    impl SlotItem for Item {
+        fn slot(&self) -> u64 {
+            self.0
+        }
+    }
+
+    fn test_channel(
+        capacity: usize,
+        policy: BackpressurePolicy,
+    ) -> (StageSender<Item>, StageReceiver<Item>) {
+        let config = StageConfig {
+            capacity: Some(capacity),
+            policy: Some(policy),
+        };
+        channel("test", &config, 1, BackpressurePolicy::DropNewest)
+    }
+
+    async fn drain(sender: StageSender<Item>, mut receiver: StageReceiver<Item>) -> Vec<Item> {
+        drop(sender);
+        let mut items = Vec::new();
+        while let Some(item) = receiver.recv().await {
+            items.push(item);
+        }
+        items
+    }
+
+    #[tokio::test]
+    async fn drop_newest_keeps_queued_items() {
+        let (sender, receiver) = test_channel(2, BackpressurePolicy::DropNewest);
+        for item in [(1, 0), (1, 1), (1, 2)] {
+            sender.send(item).await.unwrap();
+        }
+
+        assert_eq!(drain(sender, receiver).await, vec![(1, 0), (1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_slot_makes_room() {
+        let (sender, receiver) = test_channel(3, BackpressurePolicy::DropOldestSlot);
+        for item in [(2, 0), (1, 0), (2, 1), (1, 1), (3, 0)] {
+            sender.send(item).await.unwrap();
+        }
+
+        // (1, 1) belongs to the oldest slot itself, slot 1 then makes room for (3, 0)
+        assert_eq!(drain(sender, receiver).await, vec![(2, 0), (2, 1), (3, 0)]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_slot_drops_new_item_of_oldest_slot() {
+        let (sender, receiver) = test_channel(2, BackpressurePolicy::DropOldestSlot);
+        for item in [(1, 0), (2, 0), (1, 1)] {
+            sender.send(item).await.unwrap();
+        }
+
+        assert_eq!(drain(sender, receiver).await, vec![(1, 0), (2, 0)]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_slot_keeps_items_queued_after_the_drop() {
+        let (sender, mut receiver) = test_channel(2, BackpressurePolicy::DropOldestSlot);
+        for item in [(1, 0), (2, 0), (3, 0)] {
+            sender.send(item).await.unwrap();
+        }
+        assert_eq!(receiver.recv().await, Some((2, 0)));
+
+        // Slot 1 was dropped while queued, a new item of it is kept
+        sender.send((1, 1)).await.unwrap();
+        assert_eq!(drain(sender, receiver).await, vec![(3, 0), (1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_slot_compacts_dropped_items() {
+        let (sender, receiver) = test_channel(4, BackpressurePolicy::DropOldestSlot);
+        for slot in 0..100 {
+            sender.send((slot, 0)).await.unwrap();
+        }
+        let SenderQueue::Slots(queue) = &sender.queue else {
+            panic!("DropOldestSlot uses the slot queue");
+        };
+        assert!(queue.shared.lock().items.len() <= 8);
+
+        assert_eq!(
+            drain(sender, receiver).await,
+            vec![(96, 0), (97, 0), (98, 0), (99, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_room() {
+        let (sender, mut receiver) =
+            test_channel(1, BackpressurePolicy::Block { timeout_ms: None });
+        sender.send((1, 0)).await.unwrap();
+
+        let blocked = tokio::spawn(async move {
+            sender.send((1, 1)).await.unwrap();
+            sender
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+
+        assert_eq!(receiver.recv().await, Some((1, 0)));
+        let sender = blocked.await.unwrap();
+        assert_eq!(drain(sender, receiver).await, vec![(1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn block_drops_after_timeout() {
+        let (sender, receiver) = test_channel(
+            1,
+            BackpressurePolicy::Block {
+                timeout_ms: Some(10),
+            },
+        );
+        sender.send((1, 0)).await.unwrap();
+        sender.send((1, 1)).await.unwrap();
+        assert_eq!(drain(sender, receiver).await, vec![(1, 0)]);
+    }
+
+    #[tokio::test]
+    async fn blocking_send_drops_after_timeout() {
+        let (sender, receiver) = test_channel(
+            1,
+            BackpressurePolicy::Block {
+                timeout_ms: Some(10),
+            },
+        );
+        let sender = tokio::task::spawn_blocking(move || {
+            sender.blocking_send((1, 0), SendMode::Policy).unwrap();
+            let started = Instant::now();
+            sender.blocking_send((1, 1), SendMode::Policy).unwrap();
+            assert!(started.elapsed() >= Duration::from_millis(10));
+            sender
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(drain(sender, receiver).await, vec![(1, 0)]);
+    }
+
+    #[tokio::test]
+    async fn blocking_send_waits_in_wait_mode() {
+        let (sender, mut receiver) = test_channel(1, BackpressurePolicy::DropNewest);
+        let blocked = tokio::task::spawn_blocking(move || {
+            sender.blocking_send((1, 0), SendMode::Wait).unwrap();
+            sender.blocking_send((1, 1), SendMode::Wait).unwrap();
+            sender
+        });
+
+        assert_eq!(receiver.recv().await, Some((1, 0)));
+        let sender = blocked.await.unwrap();
+        assert_eq!(drain(sender, receiver).await, vec![(1, 1)]);
+    }
+
+    #[tokio::test]
+    async fn blocking_send_waits_in_wait_mode_under_drop_oldest_slot() {
+        let (sender, mut receiver) = test_channel(1, BackpressurePolicy::DropOldestSlot);
+        let blocked = tokio::task::spawn_blocking(move || {
+            sender.blocking_send((1, 0), SendMode::Wait).unwrap();
+            sender.blocking_send((2, 0), SendMode::Wait).unwrap();
+            sender
+        });
+
+        assert_eq!(receiver.recv().await, Some((1, 0)));
+        let sender = blocked.await.unwrap();
+        assert_eq!(drain(sender, receiver).await, vec![(2, 0)]);
+    }
+
+    #[tokio::test]
+    async fn send_fails_once_receiver_is_dropped() {
+        let (sender, receiver) = test_channel(1, BackpressurePolicy::Block { timeout_ms: None });
+        sender.send((1, 0)).await.unwrap();
+
+        // Wakes the sender waiting for room
+        let blocked = tokio::spawn(async move { sender.send((1, 1)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(receiver);
+
+        assert!(blocked.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn recv_returns_none_once_senders_are_dropped() {
+        let (sender, mut receiver) = test_channel(2, BackpressurePolicy::DropNewest);
+        let other = sender.clone();
+        sender.send((1, 0)).await.unwrap();
+        drop(sender);
+
+        let pending = tokio::spawn(async move {
+            let first = receiver.recv().await;
+            let second = receiver.recv().await;
+            (first, second)
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(other);
+
+        assert_eq!(pending.await.unwrap(), (Some((1, 0)), None));
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+// Furthest an unverified slot may lie ahead of the tip and move it on its own
+const MAX_TIP_JUMP: u64 = 512;
+// Distinct slots within `MAX_TIP_JUMP` of each other that move the tip further
+const JUMP_CONFIRMATIONS: usize = 4;
+// Slots too far ahead of the tip remembered until confirmed
+const MAX_JUMP_CANDIDATES: usize = 64;
+
+/// Highest slot of a completed FEC set, shared by every pipeline stage
+#[derive(Debug, Default)]
+pub struct SlotTip {
+    highest: AtomicU64,
+    max_slot_lag: Option<u64>,
+    // Completed FEC sets are signed by their slot leader, any of them moves the tip
+    verified: bool,
+    // Unverified slots too far ahead of the tip
+    jump_candidates: Mutex<BTreeSet<u64>>,
+}
+
+impl SlotTip {
+    /// * `max_slot_lag` - shed work on slots further than this behind the tip, none if unset
+    /// * `verified` - observed slots are signed by their leader, otherwise a slot further
+    ///   than `MAX_TIP_JUMP` ahead only moves the tip once `JUMP_CONFIRMATIONS` slots agree
+    pub fn new(max_slot_lag: Option<u64>, verified: bool) -> Self {
+        Self {
+            highest: AtomicU64::new(0),
+            max_slot_lag,
+            verified,
+            jump_candidates: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn observe(&self, slot: u64) {
+        // Plain load first, the tip rarely moves
+        let highest = self.highest.load(Ordering::Relaxed);
+        if slot <= highest {
+            return;
+        }
+        // The first slot is a jump from 0 as well
+        if self.verified || (highest > 0 && slot - highest <= MAX_TIP_JUMP) {
+            self.highest.fetch_max(slot, Ordering::Relaxed);
+            return;
+        }
+
+        if self.confirm_jump(slot) {
+            self.highest.fetch_max(slot, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether `slot` is preceded by enough recent candidates for a single forged
+    /// FEC set not to move the tip
+    fn confirm_jump(&self, slot: u64) -> bool {
+        let Ok(mut candidates) = self.jump_candidates.lock() else {
+            return false;
+        };
+        let highest = self.highest();
+        candidates.retain(|candidate| *candidate > highest);
+        candidates.insert(slot);
+        if candidates.len() > MAX_JUMP_CANDIDATES {
+            // The furthest ones are the least plausible
+            candidates.pop_last();
+        }
+
+        let confirmations = candidates
+            .range(slot.saturating_sub(MAX_TIP_JUMP)..=slot)
+            .count();
+        confirmations >= JUMP_CONFIRMATIONS
+    }
+
+    pub fn highest(&self) -> u64 {
+        self.highest.load(Ordering::Relaxed)
+    }
+
+    /// Whether `slot` is close enough to the tip for it to be followed soon,
+    /// any slot is until the tip is known
+    pub fn within_reach(&self, slot: u64) -> bool {
+        let highest = self.highest();
+        highest == 0 || slot <= highest.saturating_add(MAX_TIP_JUMP)
+    }
+
+    /// Whether `slot` is further than `max_slot_lag` behind the tip
+    pub fn lags(&self, slot: u64) -> bool {
+        self.max_slot_lag
+            .is_some_and(|max_slot_lag| slot.saturating_add(max_slot_lag) < self.highest())
+    }
+
+    /// Whether work on `slot` should be dropped for lagging behind the tip.
+    /// Shed items are counted per `stage`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub fn shed(&self, slot: u64, stage: &str) -> bool {
+        if !self.lags(slot) {
+            return false;
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = Metrics::try_get() {
+            metrics.slot_lag_shed.with_label_values(&[stage]).inc();
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verified_slots_move_tip() {
+        let tip = SlotTip::new(None, true);
+        tip.observe(1_000_000);
+        assert_eq!(tip.highest(), 1_000_000);
+        tip.observe(999_999);
+        assert_eq!(tip.highest(), 1_000_000);
+    }
+
+    #[test]
+    fn first_unverified_slot_needs_confirmation() {
+        let tip = SlotTip::new(None, false);
+        for slot in [100, 102, 101] {
+            tip.observe(slot);
+            assert_eq!(tip.highest(), 0);
+        }
+        tip.observe(103);
+        assert_eq!(tip.highest(), 103);
+
+        // Within reach from then on
+        tip.observe(103 + MAX_TIP_JUMP);
+        assert_eq!(tip.highest(), 103 + MAX_TIP_JUMP);
+    }
+
+    #[test]
+    fn single_far_slot_does_not_move_tip() {
+        let tip = SlotTip::new(None, false);
+        for slot in 100..104 {
+            tip.observe(slot);
+        }
+
+        tip.observe(u64::MAX - 1);
+        tip.observe(50_000);
+        tip.observe(103 + MAX_TIP_JUMP + 1);
+        assert_eq!(tip.highest(), 103);
+
+        tip.observe(104);
+        assert_eq!(tip.highest(), 104);
+    }
+
+    #[test]
+    fn confirmed_jump_moves_tip() {
+        let tip = SlotTip::new(None, false);
+        for slot in 100..104 {
+            tip.observe(slot);
+        }
+
+        // A stall of more than `MAX_TIP_JUMP` slots, the feeds catch up
+        for slot in 10_000..10_003 {
+            tip.observe(slot);
+            assert_eq!(tip.highest(), 103);
+        }
+        tip.observe(10_003);
+        assert_eq!(tip.highest(), 10_003);
+    }
+
+    #[test]
+    fn far_candidates_are_dropped_first() {
+        let tip = SlotTip::new(None, false);
+        for slot in 100..104 {
+            tip.observe(slot);
+        }
+
+        for slot in 0..2 * MAX_JUMP_CANDIDATES as u64 {
+            tip.observe(1_000_000 + slot * 1000);
+        }
+        for slot in 10_000..10_004 {
+            tip.observe(slot);
+        }
+        assert_eq!(tip.highest(), 10_003);
+    }
+
+    #[test]
+    fn lags_behind_tip() {
+        let tip = SlotTip::new(Some(10), true);
+        assert!(!tip.shed(0, "test"));
+        tip.observe(100);
+        assert!(!tip.lags(90));
+        assert!(tip.lags(89));
+        assert!(tip.shed(89, "test"));
+
+        assert!(!SlotTip::new(None, true).lags(0));
+    }
+}
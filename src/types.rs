@@ -1,7 +1,26 @@
-use std::sync::Arc;
+use crate::{header, stage::SlotItem};
+
+use std::{net::SocketAddr, sync::Arc};
 
 #[derive(Debug, Clone)]
 pub struct ShredBytesMeta {
     pub shred_bytes: Arc<Vec<u8>>,
     pub received_at_micros: Option<u64>,
+    pub origin: ShredOrigin,
+}
+
+impl SlotItem for ShredBytesMeta {
+    fn slot(&self) -> u64 {
+        // Routed shreds hold at least a shred header
+        header::slot(&self.shred_bytes).unwrap_or(0)
+    }
+}
+
+/// Where a shred was received from
+#[derive(Debug, Clone)]
+pub struct ShredOrigin {
+    /// Name of the input the shred arrived on
+    pub feed: Arc<str>,
+    /// Sender of the datagram, if known
+    pub peer_addr: Option<SocketAddr>,
 }
@@ -0,0 +1,178 @@
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{config::ShredVersionConfig, header};
+
+use ahash::HashMap;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+};
+use tracing::{info, trace};
+
+const DEFAULT_SAMPLE_SIZE: usize = 1000;
+// `detected` value while still sampling
+const UNDETECTED: u32 = u32::MAX;
+
+/// Drops shreds of other clusters by their header shred version
+pub struct ShredVersionCheck {
+    expected: Vec<u16>,
+    // Auto-detect state
+    detected: AtomicU32,
+    sample_size: usize,
+    samples: Mutex<HashMap<u16, usize>>,
+}
+
+impl ShredVersionCheck {
+    pub fn new(config: &ShredVersionConfig) -> Self {
+        let (expected, sample_size) = match config {
+            ShredVersionConfig::Fixed(versions) => (versions.clone(), 0),
+            ShredVersionConfig::AutoDetect { sample_size } => (
+                Vec::new(),
+                std::cmp::max(sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE), 1),
+            ),
+        };
+
+        Self {
+            expected,
+            detected: AtomicU32::new(UNDETECTED),
+            sample_size,
+            samples: Mutex::new(HashMap::default()),
+        }
+    }
+
+    /// Whether `buffer`, at least a shred header long, carries an accepted shred version.
+    /// While auto-detecting, every shred is accepted and sampled.
+    pub fn accepts(&self, buffer: &[u8]) -> bool {
+        let Some(version) = header::version(buffer) else {
+            return false;
+        };
+
+        let accepted = if !self.expected.is_empty() {
+            self.expected.contains(&version)
+        } else {
+            match self.detected.load(Ordering::Relaxed) {
+                UNDETECTED => {
+                    self.sample(version);
+                    true
+                }
+                detected => detected == version as u32,
+            }
+        };
+
+        if !accepted {
+            trace!("Dropped shred of shred version {}", version);
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = Metrics::try_get() {
+                metrics.receiver_shred_version_mismatches.inc();
+            }
+        }
+
+        accepted
+    }
+
+    fn sample(&self, version: u16) {
+        let Ok(mut samples) = self.samples.lock() else {
+            return;
+        };
+        if self.detected.load(Ordering::Relaxed) != UNDETECTED {
+            return;
+        }
+
+        *samples.entry(version).or_default() += 1;
+        if samples.values().sum::<usize>() < self.sample_size {
+            return;
+        }
+        let Some((&majority, &count)) = samples.iter().max_by_key(|(_, count)| **count) else {
+            return;
+        };
+        info!(
+            "Detected shred version {} ({} of {} sampled shreds)",
+            majority, count, self.sample_size
+        );
+        self.detected.store(majority as u32, Ordering::Relaxed);
+        samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{DATA_OFFSET_PAYLOAD, OFFSET_VERSION};
+
+    // Mismatches are counted in a process wide metric
+    static MISMATCHES: Mutex<()> = Mutex::new(());
+
+    fn shred(version: u16) -> Vec<u8> {
+        let mut buffer = vec![0; DATA_OFFSET_PAYLOAD];
+        buffer[OFFSET_VERSION..OFFSET_VERSION + 2].copy_from_slice(&version.to_le_bytes());
+        buffer
+    }
+
+    fn auto_detect(sample_size: usize) -> ShredVersionCheck {
+        ShredVersionCheck::new(&ShredVersionConfig::AutoDetect {
+            sample_size: Some(sample_size),
+        })
+    }
+
+    #[test]
+    fn fixed_versions() {
+        let _mismatches = MISMATCHES.lock().unwrap();
+        let check = ShredVersionCheck::new(&ShredVersionConfig::Fixed(vec![1, 2]));
+        assert!(check.accepts(&shred(1)));
+        assert!(check.accepts(&shred(2)));
+        assert!(!check.accepts(&shred(3)));
+        assert!(!check.accepts(&shred(1)[..OFFSET_VERSION + 1]));
+    }
+
+    #[test]
+    fn samples_are_accepted_until_detected() {
+        let check = auto_detect(5);
+        for version in [7, 9, 7, 9] {
+            assert!(check.accepts(&shred(version)));
+            assert_eq!(check.detected.load(Ordering::Relaxed), UNDETECTED);
+        }
+        assert!(check.accepts(&shred(7)));
+        assert_eq!(check.detected.load(Ordering::Relaxed), 7);
+        assert!(check.samples.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn mismatches_are_dropped_once_detected() {
+        let _mismatches = MISMATCHES.lock().unwrap();
+        let check = auto_detect(4);
+        for version in [3, 3, 5, 3] {
+            assert!(check.accepts(&shred(version)));
+        }
+
+        assert!(check.accepts(&shred(3)));
+        assert!(!check.accepts(&shred(5)));
+        assert!(!check.accepts(&shred(4)));
+        assert!(!check.accepts(&shred(3)[..OFFSET_VERSION + 1]));
+    }
+
+    #[test]
+    fn sample_size_is_at_least_one() {
+        let _mismatches = MISMATCHES.lock().unwrap();
+        let check = auto_detect(0);
+        assert!(check.accepts(&shred(2)));
+        assert!(!check.accepts(&shred(1)));
+        assert!(check.accepts(&shred(2)));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn mismatches_are_counted() {
+        let _mismatches = MISMATCHES.lock().unwrap();
+        let _ = Metrics::init_with_registry(std::sync::Arc::new(prometheus::Registry::new()));
+        let counter = &Metrics::try_get()
+            .unwrap()
+            .receiver_shred_version_mismatches;
+        let before = counter.get();
+
+        let check = ShredVersionCheck::new(&ShredVersionConfig::Fixed(vec![1]));
+        check.accepts(&shred(1));
+        check.accepts(&shred(2));
+        check.accepts(&shred(3));
+        assert_eq!(counter.get() - before, 2);
+    }
+}